@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use dominoes::sink::Ignore;
+use dominoes::tokenizer::TokenizerImpl;
+
+const CHUNK: &str = "<div class=\"product\" data-id='42'>\r\n\
+    <!-- a comment -->\r\n\
+    <a href=\"/p?id=42&amp;ref=home\">Caf\u{E9} &copy; 2020 &#x2014; &lt;new&gt;</a>\r\n\
+    <img src=x alt=\"\" /><br>\r\n\
+</div>\r\n";
+
+/// The throughput should stay about the same as the input gets bigger, if tokenizing takes linear
+/// time
+fn tokenize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    for size in &[16 * 1024, 64 * 1024, 256 * 1024, 1024 * 1024] {
+        let input = CHUNK.repeat(size / CHUNK.len() + 1);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| TokenizerImpl::new(input.clone(), Ignore).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tokenize);
+criterion_main!(benches);
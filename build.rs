@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const ENTITIES_PATH: &str = "data/entities.json";
+const GENERATED_FILE_NAME: &str = "named_character_references.rs";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", ENTITIES_PATH);
+
+    let json = fs::read_to_string(ENTITIES_PATH).expect("failed to read the entities table");
+    let mut trie = Trie::default();
+    for (name, characters) in parse_entities(&json) {
+        trie.insert(&name, characters);
+    }
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_path = Path::new(&out_dir).join(GENERATED_FILE_NAME);
+    fs::write(out_path, trie.generate()).expect("failed to write the generated trie");
+}
+
+/// Parses the `"&name;": { "codepoints": [...], "characters": "..." }` entries of the WHATWG
+/// `entities.json` file. Each entry is on its own line, so there is no need for a real JSON parser.
+fn parse_entities(json: &str) -> Vec<(String, String)> {
+    let entities: Vec<_> = json
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("\"&"))
+        .map(|line| {
+            let name_end = line[1..].find('"').unwrap() + 1;
+            let name = &line[2..name_end];
+
+            let codepoints_start = line.find('[').unwrap() + 1;
+            let codepoints_end = line.find(']').unwrap();
+            let characters = line[codepoints_start..codepoints_end]
+                .split(',')
+                .map(|codepoint| codepoint.trim().parse::<u32>().unwrap())
+                .map(|codepoint| std::char::from_u32(codepoint).unwrap())
+                .collect();
+            (name.to_owned(), characters)
+        })
+        .collect();
+    assert!(!entities.is_empty(), "no entities found");
+    entities
+}
+
+#[derive(Default)]
+struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: BTreeMap<u8, usize>,
+    characters: Option<String>,
+}
+
+impl Trie {
+    fn insert(&mut self, name: &str, characters: String) {
+        if self.nodes.is_empty() {
+            self.nodes.push(TrieNode::default());
+        }
+        let mut node_idx = 0;
+        for byte in name.bytes() {
+            assert!(byte.is_ascii_alphanumeric() || byte == b';');
+            node_idx = match self.nodes[node_idx].children.get(&byte) {
+                Some(&child_idx) => child_idx,
+                None => {
+                    let child_idx = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node_idx].children.insert(byte, child_idx);
+                    child_idx
+                }
+            };
+        }
+        self.nodes[node_idx].characters = Some(characters);
+    }
+
+    /// Flattens the trie into static arrays: each node refers to a contiguous range of edges
+    /// (sorted by byte, so they can be binary searched) and to an optional index into the
+    /// characters array (offset by one, so zero can mean "not a complete name").
+    fn generate(&self) -> String {
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        let mut characters = String::new();
+        let mut edge_count = 0;
+        let mut characters_count = 0;
+
+        for node in &self.nodes {
+            let first_edge = edge_count;
+            for (byte, child_idx) in &node.children {
+                writeln!(edges, "    ({}, {}),", byte, child_idx).unwrap();
+                edge_count += 1;
+            }
+            let characters_idx = match &node.characters {
+                Some(it) => {
+                    writeln!(characters, "    {:?},", it).unwrap();
+                    characters_count += 1;
+                    characters_count
+                }
+                None => 0,
+            };
+            writeln!(
+                nodes,
+                "    ({}, {}, {}),",
+                first_edge, edge_count, characters_idx
+            )
+            .unwrap();
+        }
+        assert!(self.nodes.len() <= usize::from(u16::MAX));
+        assert!(edge_count <= usize::from(u16::MAX));
+
+        let mut out = String::new();
+        writeln!(out, "// @generated by build.rs from {}", ENTITIES_PATH).unwrap();
+        writeln!(
+            out,
+            "static NODES: [(u16, u16, u16); {}] = [\n{}];",
+            self.nodes.len(),
+            nodes
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "static EDGES: [(u8, u16); {}] = [\n{}];",
+            edge_count, edges
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "static CHARACTERS: [&str; {}] = [\n{}];",
+            characters_count, characters
+        )
+        .unwrap();
+        out
+    }
+}
@@ -0,0 +1,27 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// Why a change to a tree was rejected. These correspond to the exceptions the DOM spec's mutation
+/// algorithms throw.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MutationError {
+    /// The node would become its own ancestor (eg. an element appended to one of its descendants)
+    Cycle,
+    /// The node can't go there, eg. text directly in a document, a second element in a document,
+    /// or anything in a text node
+    InvalidHierarchy,
+    /// The reference child (or the child to be removed or replaced) isn't a child of the parent
+    NotFound,
+}
+
+impl Error for MutationError {}
+
+impl Display for MutationError {
+    //! ```
+    //! use dominoes::dom::error::MutationError;
+    //! assert_eq!(format!("{}", MutationError::Cycle), "Cycle");
+    //! ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
@@ -3,53 +3,799 @@ use std::rc::{Rc, Weak};
 
 use NodeData::*;
 
+use error::MutationError;
+
+pub use serialize::serialize;
+
+pub mod error;
+
+mod serialize;
+
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug)]
 pub struct Node {
     pub(crate) data: NodeData,
     pub(crate) document: Option<Weak<RefCell<Self>>>,
     pub(crate) parent: Option<Weak<RefCell<Self>>>,
+    /// The `<template>` whose contents this is, for a template's document fragment
+    pub(crate) host: Option<Weak<RefCell<Self>>>,
     pub(crate) children: Vec<Rc<RefCell<Self>>>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Namespace {
+    Html,
+    MathMl,
+    Svg,
+    /// Only used for attributes (eg. `xlink:href`)
+    XLink,
+    /// Only used for attributes (eg. `xml:lang`)
+    Xml,
+    /// Only used for attributes (eg. `xmlns:xlink`)
+    XmlNs,
+    /// Any other namespace, by its URL. The parser never creates these.
+    Other(String),
+}
+
+/// An element's attribute. Most attributes have neither a namespace nor a prefix, but eg.
+/// `xlink:href` on an SVG element has both.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Attribute {
+    namespace: Option<Namespace>,
+    prefix: Option<String>,
+    local_name: String,
+    value: String,
+}
+
+/// Whether the document is rendered compatibly with old browsers, as decided by its DOCTYPE
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum QuirksMode {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum NodeData {
-    Document,
+    Document {
+        quirks_mode: QuirksMode,
+    },
     Doctype {
         name: String,
         public_identifier: String,
-        private_identifier: String,
-    },
-    Element {
-        tag_name: String,
+        system_identifier: String,
     },
+    Element(ElementData),
     Text(String),
     Comment(String),
+    /// Only used for a `<template>`'s contents
+    DocumentFragment,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ElementData {
+    pub(crate) namespace: Namespace,
+    pub(crate) prefix: Option<String>,
+    pub(crate) local_name: String,
+    /// In source order
+    pub(crate) attributes: Vec<Attribute>,
+    /// The document fragment holding a `<template>`'s contents. `None` for any other element.
+    pub(crate) template_contents: Option<Rc<RefCell<Node>>>,
 }
 
 impl Node {
     pub fn create_document() -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(Self {
-            data: Document,
+            data: Document {
+                quirks_mode: QuirksMode::NoQuirks,
+            },
             document: None,
             parent: None,
+            host: None,
             children: Vec::new(),
         }))
     }
 
+    /// Creates an element in the HTML namespace
     pub fn create_element(
-        tag_name: String,
+        local_name: String,
+        attributes: Vec<Attribute>,
+        document: &Rc<RefCell<Self>>,
+        parent: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        Self::create_element_ns(
+            Namespace::Html,
+            None,
+            local_name,
+            attributes,
+            document,
+            parent,
+        )
+    }
+
+    /// Like `create_element`, but for an element in any namespace
+    pub fn create_element_ns(
+        namespace: Namespace,
+        prefix: Option<String>,
+        local_name: String,
+        attributes: Vec<Attribute>,
         document: &Rc<RefCell<Self>>,
         parent: &Rc<RefCell<Self>>,
     ) -> Rc<RefCell<Self>> {
+        let elem = Self::create_detached_element(namespace, local_name, attributes, document);
+        if let Element(element) = &mut elem.borrow_mut().data {
+            element.prefix = prefix;
+        }
+        Self::append(parent, &elem);
+        elem
+    }
+
+    /// Creates an element without a prefix which isn't in the tree yet
+    pub(crate) fn create_detached_element(
+        namespace: Namespace,
+        local_name: String,
+        attributes: Vec<Attribute>,
+        document: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        let template_contents = (namespace == Namespace::Html && local_name == "template")
+            .then(|| Self::create_document_fragment(document));
         let elem = Rc::new(RefCell::new(Self {
-            data: Element { tag_name },
+            data: Element(ElementData {
+                namespace,
+                prefix: None,
+                local_name,
+                attributes,
+                template_contents,
+            }),
             document: Some(Rc::downgrade(document)),
-            parent: Some(Rc::downgrade(parent)),
+            parent: None,
+            host: None,
             children: Vec::new(),
         }));
-        RefCell::borrow_mut(parent).children.push(elem.clone());
+        Self::link_template_contents(&elem);
         elem
     }
+
+    /// Creates a copy of the element (but not its children, or a `<template>`'s contents) which
+    /// isn't in the tree yet
+    pub(crate) fn clone_element(element: &Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let element = element.borrow();
+        let mut data = match &element.data {
+            Element(data) => data.clone(),
+            _ => panic!("Expected an element but was: {:?}", element.data),
+        };
+        if data.template_contents.is_some() {
+            let document = element
+                .owner_document()
+                .expect("the element's document has been dropped");
+            data.template_contents = Some(Self::create_document_fragment(&document));
+        }
+        let clone = Rc::new(RefCell::new(Self {
+            data: Element(data),
+            document: element.document.clone(),
+            parent: None,
+            host: None,
+            children: Vec::new(),
+        }));
+        Self::link_template_contents(&clone);
+        clone
+    }
+
+    /// Points a `<template>`'s contents back at the template, which the contents are considered to
+    /// be inside of when checking for cycles
+    fn link_template_contents(element: &Rc<RefCell<Self>>) {
+        if let Some(contents) = element.borrow().template_contents() {
+            contents.borrow_mut().host = Some(Rc::downgrade(element));
+        }
+    }
+
+    fn create_document_fragment(document: &Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            data: DocumentFragment,
+            document: Some(Rc::downgrade(document)),
+            parent: None,
+            host: None,
+            children: Vec::new(),
+        }))
+    }
+
+    pub fn create_doctype(
+        name: String,
+        public_identifier: String,
+        system_identifier: String,
+        document: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        let doctype = Rc::new(RefCell::new(Self {
+            data: Doctype {
+                name,
+                public_identifier,
+                system_identifier,
+            },
+            document: Some(Rc::downgrade(document)),
+            parent: None,
+            host: None,
+            children: Vec::new(),
+        }));
+        Self::append(document, &doctype);
+        doctype
+    }
+
+    pub fn create_comment(
+        data: String,
+        document: &Rc<RefCell<Self>>,
+        parent: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        let comment = Self::create_detached_comment(data, document);
+        Self::append(parent, &comment);
+        comment
+    }
+
+    /// Creates a comment which isn't in the tree yet
+    pub(crate) fn create_detached_comment(
+        data: String,
+        document: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            data: Comment(data),
+            document: Some(Rc::downgrade(document)),
+            parent: None,
+            host: None,
+            children: Vec::new(),
+        }))
+    }
+
+    /// Appends the text to the parent's last child if that is a text node already, so adjacent text
+    /// is always merged into a single node
+    pub fn create_text(
+        data: &str,
+        document: &Rc<RefCell<Self>>,
+        parent: &Rc<RefCell<Self>>,
+    ) -> Rc<RefCell<Self>> {
+        Self::insert_text(data, document, parent, None)
+    }
+
+    /// Like `create_text`, but inserts the text just before the reference child if there is one
+    /// (merging it into the text node before that instead)
+    pub(crate) fn insert_text(
+        data: &str,
+        document: &Rc<RefCell<Self>>,
+        parent: &Rc<RefCell<Self>>,
+        reference_child: Option<&Rc<RefCell<Self>>>,
+    ) -> Rc<RefCell<Self>> {
+        let previous_sibling = {
+            let parent = parent.borrow();
+            match reference_child {
+                Some(reference_child) => {
+                    let index = parent
+                        .children
+                        .iter()
+                        .position(|node| Rc::ptr_eq(node, reference_child))
+                        .expect("the reference node isn't a child of the parent");
+                    index
+                        .checked_sub(1)
+                        .map(|index| parent.children[index].clone())
+                }
+                None => parent.children.last().cloned(),
+            }
+        };
+        if let Some(previous_sibling) = previous_sibling {
+            if let Text(text) = &mut previous_sibling.borrow_mut().data {
+                text.push_str(data);
+                return previous_sibling.clone();
+            }
+        }
+        let text = Rc::new(RefCell::new(Self {
+            data: Text(String::from(data)),
+            document: Some(Rc::downgrade(document)),
+            parent: None,
+            host: None,
+            children: Vec::new(),
+        }));
+        match reference_child {
+            Some(reference_child) => Self::insert(parent, &text, reference_child),
+            None => Self::append(parent, &text),
+        }
+        text
+    }
+
+    pub fn data(&self) -> &NodeData {
+        &self.data
+    }
+
+    /// Returns `None` if this isn't an element
+    pub fn as_element(&self) -> Option<&ElementData> {
+        match &self.data {
+            Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if this isn't a text node
+    pub fn as_text(&self) -> Option<&str> {
+        match &self.data {
+            Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if this isn't a comment
+    pub fn as_comment(&self) -> Option<&str> {
+        match &self.data {
+            Comment(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if this isn't an element
+    pub fn namespace(&self) -> Option<&Namespace> {
+        self.as_element().map(ElementData::namespace)
+    }
+
+    /// Returns `None` if this isn't an element, or is one without a prefix
+    pub fn prefix(&self) -> Option<&str> {
+        self.as_element().and_then(ElementData::prefix)
+    }
+
+    /// Returns `None` if this isn't an element
+    pub fn local_name(&self) -> Option<&str> {
+        self.as_element().map(ElementData::local_name)
+    }
+
+    /// Returns `None` if this isn't an element
+    pub fn attributes(&self) -> Option<&[Attribute]> {
+        self.as_element().map(ElementData::attributes)
+    }
+
+    /// Returns `None` if this isn't an element, or is one without such an attribute
+    pub fn attribute(&self, qualified_name: &str) -> Option<&str> {
+        self.as_element()?.attribute(qualified_name)
+    }
+
+    /// Returns `None` if this isn't a `<template>` element
+    pub fn template_contents(&self) -> Option<&Rc<RefCell<Self>>> {
+        self.as_element()?.template_contents()
+    }
+
+    /// Returns `None` if this isn't an element
+    pub(crate) fn attributes_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        match &mut self.data {
+            Element(element) => Some(&mut element.attributes),
+            _ => None,
+        }
+    }
+
+    pub fn children(&self) -> &[Rc<RefCell<Self>>] {
+        &self.children
+    }
+
+    /// Returns `None` if this node isn't in a tree, or is the root of one
+    pub fn parent(&self) -> Option<Rc<RefCell<Self>>> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// The document which created this node. Returns `None` if this is a document, or the document
+    /// has been dropped.
+    pub fn owner_document(&self) -> Option<Rc<RefCell<Self>>> {
+        self.document.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// Iterates over the node's descendants in tree order (each node before its children), not
+    /// including the node itself
+    pub fn descendants(&self) -> Descendants {
+        Descendants {
+            stack: self.children.iter().rev().cloned().collect(),
+        }
+    }
+
+    /// Iterates over the node's parent, the parent's parent and so on, up to the root
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// Returns `None` if this is its parent's last child, or doesn't have a parent
+    pub fn following_sibling(&self) -> Option<Rc<RefCell<Self>>> {
+        self.sibling(1)
+    }
+
+    /// Returns `None` if this is its parent's first child, or doesn't have a parent
+    pub fn preceding_sibling(&self) -> Option<Rc<RefCell<Self>>> {
+        self.sibling(-1)
+    }
+
+    fn sibling(&self, offset: isize) -> Option<Rc<RefCell<Self>>> {
+        let parent = self.parent()?;
+        let parent = parent.borrow();
+        let index = parent
+            .children
+            .iter()
+            .position(|child| std::ptr::eq(child.as_ptr(), self))
+            .expect("the node isn't a child of its parent");
+        let index = index.checked_add_signed(offset)?;
+        parent.children.get(index).cloned()
+    }
+
+    /// Moves the node to the end of the parent's children, removing it from its old parent (and
+    /// adopting it into the parent's document) first
+    pub fn append_child(
+        parent: &Rc<RefCell<Self>>,
+        node: &Rc<RefCell<Self>>,
+    ) -> Result<(), MutationError> {
+        Self::insert_before(parent, node, None)
+    }
+
+    /// Moves the node to just before the child (or to the end of the parent's children if there
+    /// isn't one), removing it from its old parent (and adopting it into the parent's document)
+    /// first
+    pub fn insert_before(
+        parent: &Rc<RefCell<Self>>,
+        node: &Rc<RefCell<Self>>,
+        child: Option<&Rc<RefCell<Self>>>,
+    ) -> Result<(), MutationError> {
+        ensure_insertion_validity(parent, node, child, false)?;
+        let reference_child = match child {
+            Some(child) if Rc::ptr_eq(child, node) => node.borrow().following_sibling(),
+            _ => child.cloned(),
+        };
+        Self::adopt_into_document_of(parent, node);
+        match &reference_child {
+            Some(reference_child) => Self::insert(parent, node, reference_child),
+            None => Self::append(parent, node),
+        }
+        Ok(())
+    }
+
+    /// Removes the child from the parent. The child stays in the parent's document.
+    pub fn remove_child(
+        parent: &Rc<RefCell<Self>>,
+        child: &Rc<RefCell<Self>>,
+    ) -> Result<(), MutationError> {
+        if !is_child(parent, child) {
+            return Err(MutationError::NotFound);
+        }
+        Self::detach(child);
+        Ok(())
+    }
+
+    /// Puts the node in the child's place, removing the node from its old parent (and adopting it
+    /// into the parent's document) first
+    pub fn replace_child(
+        parent: &Rc<RefCell<Self>>,
+        node: &Rc<RefCell<Self>>,
+        child: &Rc<RefCell<Self>>,
+    ) -> Result<(), MutationError> {
+        ensure_insertion_validity(parent, node, Some(child), true)?;
+        if Rc::ptr_eq(node, child) {
+            return Ok(());
+        }
+        Self::adopt_into_document_of(parent, node);
+        Self::insert(parent, node, child);
+        Self::detach(child);
+        Ok(())
+    }
+
+    /// Removes the node from its parent, and moves it (along with its descendants) into the
+    /// document
+    pub fn adopt(
+        document: &Rc<RefCell<Self>>,
+        node: &Rc<RefCell<Self>>,
+    ) -> Result<(), MutationError> {
+        if !matches!(document.borrow().data, Document { .. })
+            || matches!(node.borrow().data, Document { .. } | DocumentFragment)
+        {
+            return Err(MutationError::InvalidHierarchy);
+        }
+        Self::detach(node);
+        let mut stack = vec![node.clone()];
+        while let Some(node) = stack.pop() {
+            node.borrow_mut().document = Some(Rc::downgrade(document));
+            // a `<template>`'s contents move along with it
+            let node = node.borrow();
+            stack.extend(
+                node.children
+                    .iter()
+                    .chain(node.template_contents())
+                    .cloned(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Adopts the node into the parent's document (if that still exists), unless it is in there
+    /// already
+    fn adopt_into_document_of(parent: &Rc<RefCell<Self>>, node: &Rc<RefCell<Self>>) {
+        let document = match parent.borrow().data {
+            Document { .. } => Some(parent.clone()),
+            _ => parent.borrow().owner_document(),
+        };
+        if let Some(document) = document {
+            let in_document = node
+                .borrow()
+                .owner_document()
+                .is_some_and(|owner_document| Rc::ptr_eq(&owner_document, &document));
+            if !in_document {
+                Self::adopt(&document, node).unwrap();
+            }
+        }
+    }
+
+    /// Moves the child to the end of the parent's children, removing it from its old parent first.
+    /// Unlike `append_child`, this doesn't check the tree stays valid.
+    pub(crate) fn append(parent: &Rc<RefCell<Self>>, child: &Rc<RefCell<Self>>) {
+        Self::detach(child);
+        child.borrow_mut().parent = Some(Rc::downgrade(parent));
+        parent.borrow_mut().children.push(child.clone());
+    }
+
+    /// Moves the child to just before the reference child, removing it from its old parent first.
+    /// Unlike `insert_before`, this doesn't check the tree stays valid.
+    pub(crate) fn insert(
+        parent: &Rc<RefCell<Self>>,
+        child: &Rc<RefCell<Self>>,
+        reference_child: &Rc<RefCell<Self>>,
+    ) {
+        Self::detach(child);
+        child.borrow_mut().parent = Some(Rc::downgrade(parent));
+        let mut parent = parent.borrow_mut();
+        let index = parent
+            .children
+            .iter()
+            .position(|node| Rc::ptr_eq(node, reference_child))
+            .expect("the reference node isn't a child of the parent");
+        parent.children.insert(index, child.clone());
+    }
+
+    /// Removes the node from its parent, if it has one
+    pub(crate) fn detach(node: &Rc<RefCell<Self>>) {
+        let parent = node.borrow_mut().parent.take();
+        if let Some(parent) = parent.and_then(|parent| parent.upgrade()) {
+            parent
+                .borrow_mut()
+                .children
+                .retain(|child| !Rc::ptr_eq(child, node));
+        }
+    }
+
+    /// Returns `None` if this isn't a document
+    pub fn quirks_mode(&self) -> Option<QuirksMode> {
+        match self.data {
+            Document { quirks_mode } => Some(quirks_mode),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        match &mut self.data {
+            Document { quirks_mode } => *quirks_mode = mode,
+            _ => panic!("Expected a document but was: {:?}", self.data),
+        }
+    }
+}
+
+fn is_child(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) -> bool {
+    child
+        .borrow()
+        .parent()
+        .is_some_and(|child_parent| Rc::ptr_eq(&child_parent, parent))
+}
+
+/// Whether the node is the other node or one of its ancestors, where a `<template>`'s contents count
+/// as being inside the template
+fn is_host_including_inclusive_ancestor(
+    node: &Rc<RefCell<Node>>,
+    other: &Rc<RefCell<Node>>,
+) -> bool {
+    let mut current = Some(other.clone());
+    while let Some(ancestor) = current {
+        if Rc::ptr_eq(&ancestor, node) {
+            return true;
+        }
+        let ancestor = ancestor.borrow();
+        current = ancestor
+            .parent()
+            .or_else(|| ancestor.host.as_ref().and_then(Weak::upgrade));
+    }
+    false
+}
+
+/// Checks the node can be inserted before the child (or appended, if there is no child), or
+/// replace the child
+fn ensure_insertion_validity(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+    replacing: bool,
+) -> Result<(), MutationError> {
+    let parent_is_document = match parent.borrow().data {
+        Document { .. } => true,
+        Element(_) | DocumentFragment => false,
+        _ => return Err(MutationError::InvalidHierarchy),
+    };
+    if is_host_including_inclusive_ancestor(node, parent) {
+        return Err(MutationError::Cycle);
+    }
+    if let Some(child) = child {
+        if !is_child(parent, child) {
+            return Err(MutationError::NotFound);
+        }
+    }
+    match node.borrow().data {
+        Document { .. } | DocumentFragment => return Err(MutationError::InvalidHierarchy),
+        Text(_) if parent_is_document => return Err(MutationError::InvalidHierarchy),
+        Doctype { .. } if !parent_is_document => return Err(MutationError::InvalidHierarchy),
+        Element(_) | Doctype { .. } if parent_is_document => {}
+        _ => return Ok(()),
+    }
+
+    // a document has at most one DOCTYPE and one element, in that order
+    let parent = parent.borrow();
+    let is_element = |node: &Rc<RefCell<Node>>| matches!(node.borrow().data, Element(_));
+    let is_doctype = |node: &Rc<RefCell<Node>>| matches!(node.borrow().data, Doctype { .. });
+    let index = child.map(|child| {
+        parent
+            .children
+            .iter()
+            .position(|node| Rc::ptr_eq(node, child))
+            .unwrap()
+    });
+    let others = || {
+        parent
+            .children
+            .iter()
+            .filter(|node| !(replacing && child.is_some_and(|child| Rc::ptr_eq(node, child))))
+    };
+    let (preceding, following) = match index {
+        Some(index) if replacing => (&parent.children[..index], &parent.children[index + 1..]),
+        Some(index) => (&parent.children[..index], &parent.children[index..]),
+        None => (&parent.children[..], &parent.children[..0]),
+    };
+    let valid = if is_element(node) {
+        !others().any(is_element) && !following.iter().any(is_doctype)
+    } else {
+        !others().any(is_doctype) && !preceding.iter().any(is_element)
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(MutationError::InvalidHierarchy)
+    }
+}
+
+impl ElementData {
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// The element's name without its prefix (eg. `div` or `foreignObject`)
+    pub fn local_name(&self) -> &str {
+        &self.local_name
+    }
+
+    /// In source order
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Returns the value of the attribute with the given qualified name (eg. `xlink:href`), if
+    /// there is one
+    pub fn attribute(&self, qualified_name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.has_qualified_name(qualified_name))
+            .map(Attribute::value)
+    }
+
+    /// Returns `None` if this isn't a `<template>` element
+    pub fn template_contents(&self) -> Option<&Rc<RefCell<Node>>> {
+        self.template_contents.as_ref()
+    }
+}
+
+/// Iterates over a node's descendants in tree order
+pub struct Descendants {
+    /// The nodes still to be visited, the next one last
+    stack: Vec<Rc<RefCell<Node>>>,
+}
+
+impl Iterator for Descendants {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack
+            .extend(node.borrow().children.iter().rev().cloned());
+        Some(node)
+    }
+}
+
+/// Iterates over a node's ancestors, nearest first
+pub struct Ancestors {
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+impl Iterator for Ancestors {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next.take()?;
+        self.next = node.borrow().parent();
+        Some(node)
+    }
+}
+
+impl Namespace {
+    pub fn url(&self) -> &str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::XLink => "http://www.w3.org/1999/xlink",
+            Namespace::Xml => "http://www.w3.org/XML/1998/namespace",
+            Namespace::XmlNs => "http://www.w3.org/2000/xmlns/",
+            Namespace::Other(url) => url,
+        }
+    }
+}
+
+impl Attribute {
+    /// Creates an attribute without a namespace
+    pub fn new(local_name: String, value: String) -> Self {
+        Self {
+            namespace: None,
+            prefix: None,
+            local_name,
+            value,
+        }
+    }
+
+    /// Creates an attribute in the namespace, eg. `xlink:href` is in the XLink namespace with the
+    /// prefix `xlink`
+    pub fn new_ns(
+        namespace: Namespace,
+        prefix: Option<String>,
+        local_name: String,
+        value: String,
+    ) -> Self {
+        Self {
+            namespace: Some(namespace),
+            prefix,
+            local_name,
+            value,
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&Namespace> {
+        self.namespace.as_ref()
+    }
+
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    pub fn local_name(&self) -> &str {
+        &self.local_name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    fn has_qualified_name(&self, qualified_name: &str) -> bool {
+        match &self.prefix {
+            Some(prefix) => qualified_name
+                .strip_prefix(prefix.as_str())
+                .and_then(|rest| rest.strip_prefix(':'))
+                .is_some_and(|local_name| local_name == self.local_name),
+            None => qualified_name == self.local_name,
+        }
+    }
 }
 
 impl PartialEq for Node {
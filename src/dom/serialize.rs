@@ -0,0 +1,127 @@
+//! Turns a tree back into HTML, following the HTML fragment serialization algorithm
+
+use std::io::{self, Write};
+
+use super::NodeData::*;
+use super::{Attribute, Namespace, Node};
+
+/// HTML elements which never have an end tag (or any content)
+const VOID_ELEMENTS: [&str; 18] = [
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// HTML elements whose text is written out as it is, without escaping
+const RAW_TEXT_ELEMENTS: [&str; 7] = [
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Writes out the node's children as HTML, like `innerHTML`. A `<template>` has its contents
+/// written instead, and void elements (eg. `<br>`) have no children to write.
+pub fn serialize(node: &Node, mut writer: impl Write) -> io::Result<()> {
+    serialize_children(node, &mut writer)
+}
+
+impl Node {
+    /// Returns the node's children as HTML. See [`serialize`].
+    pub fn to_html(&self) -> String {
+        let mut html = Vec::new();
+        serialize(self, &mut html).expect("writing to a Vec never fails");
+        String::from_utf8(html).expect("only valid UTF-8 is written")
+    }
+}
+
+fn serialize_children<W: Write>(node: &Node, writer: &mut W) -> io::Result<()> {
+    if is_html_element(node, &VOID_ELEMENTS) {
+        return Ok(());
+    }
+    if let Some(contents) = node.template_contents() {
+        return serialize_children(&contents.borrow(), writer);
+    }
+    for child in &node.children {
+        let child = child.borrow();
+        match &child.data {
+            Element(element) => {
+                let local_name = element.local_name();
+                let tag_name = match (element.namespace(), element.prefix()) {
+                    (Namespace::Html | Namespace::MathMl | Namespace::Svg, _) | (_, None) => {
+                        String::from(local_name)
+                    }
+                    (_, Some(prefix)) => format!("{}:{}", prefix, local_name),
+                };
+                write!(writer, "<{}", tag_name)?;
+                for attribute in element.attributes() {
+                    write!(writer, " {}=\"", attribute_name(attribute))?;
+                    write_escaped(writer, attribute.value(), true)?;
+                    write!(writer, "\"")?;
+                }
+                write!(writer, ">")?;
+                if is_html_element(&child, &VOID_ELEMENTS) {
+                    continue;
+                }
+                serialize_children(&child, writer)?;
+                write!(writer, "</{}>", tag_name)?;
+            }
+            Text(text) => {
+                if is_html_element(node, &RAW_TEXT_ELEMENTS) {
+                    writer.write_all(text.as_bytes())?;
+                } else {
+                    write_escaped(writer, text, false)?;
+                }
+            }
+            Comment(data) => write!(writer, "<!--{}-->", data)?,
+            Doctype { name, .. } => write!(writer, "<!DOCTYPE {}>", name)?,
+            Document { .. } | DocumentFragment => {
+                panic!("A document (or document fragment) can't be the child of another node")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Attributes in the XML, XMLNS and XLink namespaces are written with their usual prefix, whatever
+/// prefix they actually have
+fn attribute_name(attribute: &Attribute) -> String {
+    let local_name = attribute.local_name();
+    match (attribute.namespace(), attribute.prefix()) {
+        (None, _) => String::from(local_name),
+        (Some(Namespace::Xml), _) => format!("xml:{}", local_name),
+        (Some(Namespace::XmlNs), _) if local_name == "xmlns" => String::from("xmlns"),
+        (Some(Namespace::XmlNs), _) => format!("xmlns:{}", local_name),
+        (Some(Namespace::XLink), _) => format!("xlink:{}", local_name),
+        (Some(_), Some(prefix)) => format!("{}:{}", prefix, local_name),
+        (Some(_), None) => String::from(local_name),
+    }
+}
+
+/// Escapes `&`, no-break spaces, `<` and `>`, along with `"` in attribute values
+fn write_escaped<W: Write>(writer: &mut W, text: &str, attribute_mode: bool) -> io::Result<()> {
+    let mut start = 0;
+    for (index, ch) in text.char_indices() {
+        let escaped = match ch {
+            '&' => "&amp;",
+            '\u{00A0}' => "&nbsp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' if attribute_mode => "&quot;",
+            _ => continue,
+        };
+        writer.write_all(&text.as_bytes()[start..index])?;
+        writer.write_all(escaped.as_bytes())?;
+        start = index + ch.len_utf8();
+    }
+    writer.write_all(&text.as_bytes()[start..])
+}
+
+fn is_html_element(node: &Node, local_names: &[&str]) -> bool {
+    node.namespace() == Some(&Namespace::Html)
+        && node
+            .local_name()
+            .is_some_and(|local_name| local_names.contains(&local_name))
+}
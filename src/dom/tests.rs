@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::error::MutationError;
+use crate::dom::{serialize, Attribute, Namespace, Node};
+use crate::parser::Parser;
+use crate::sink::Ignore;
+use crate::tokenizer::TokenizerImpl;
+
+#[test]
+fn serialize_document() {
+    let input = concat!(
+        "<!DOCTYPE html><html><head><title>a &amp; b</title></head>",
+        "<body class=\"x\"><!--c--><p title='&quot;&nbsp;&lt;'>d<br>e</p>",
+        "<script>if (a < b) {}</script><img src=f.png></body></html>",
+    );
+    let document = parse(input);
+
+    let expected = concat!(
+        "<!DOCTYPE html><html><head><title>a &amp; b</title></head>",
+        "<body class=\"x\"><!--c--><p title=\"&quot;&nbsp;&lt;\">d<br>e</p>",
+        "<script>if (a < b) {}</script><img src=\"f.png\"></body></html>",
+    );
+    assert_eq!(document.borrow().to_html(), expected);
+
+    let mut html = Vec::new();
+    serialize(&document.borrow(), &mut html).unwrap();
+    assert_eq!(html, expected.as_bytes());
+}
+
+#[test]
+fn serialize_element_contents() {
+    let document = parse("<div>a<template><b>b</b></template></div><br>");
+
+    let html = document.borrow().children[0].clone();
+    let body = html.borrow().children[1].clone();
+    let div = body.borrow().children[0].clone();
+    let br = body.borrow().children[1].clone();
+    let template = div.borrow().children[1].clone();
+    assert_eq!(div.borrow().to_html(), "a<template><b>b</b></template>");
+    assert_eq!(br.borrow().to_html(), "");
+    assert!(template.borrow().children().is_empty());
+    assert_eq!(template.borrow().to_html(), "<b>b</b>");
+    let contents = template.borrow().template_contents().unwrap().clone();
+    assert_eq!(contents.borrow().to_html(), "<b>b</b>");
+}
+
+#[test]
+fn serialize_foreign_content() {
+    let document = parse("<svg viewbox='0 0 1 1'><a xlink:href='#a'><path/></a></svg>");
+
+    let html = document.borrow().children[0].clone();
+    let body = html.borrow().children[1].clone();
+    assert_eq!(
+        body.borrow().to_html(),
+        "<svg viewBox=\"0 0 1 1\"><a xlink:href=\"#a\"><path></path></a></svg>"
+    );
+}
+
+#[test]
+fn serialize_other_namespace() {
+    let document = Node::create_document();
+    let parent = Node::create_element("div".into(), Vec::new(), &document, &document);
+    let namespace = Namespace::Other("urn:a".into());
+    let attribute = Attribute::new_ns(
+        Namespace::Other("urn:c".into()),
+        Some("c".into()),
+        "d".into(),
+        "e".into(),
+    );
+    let child = Node::create_element_ns(
+        namespace,
+        Some("a".into()),
+        "b".into(),
+        vec![attribute],
+        &document,
+        &parent,
+    );
+    Node::create_text("<", &document, &child);
+
+    assert_eq!(parent.borrow().to_html(), "<a:b c:d=\"e\">&lt;</a:b>");
+}
+
+#[test]
+fn round_trip() {
+    let input = "<p>a<b>b<i>c</b>d</i><table>e<tr><td>f</table><textarea>\n<g></textarea>";
+    let normalised = parse(input).borrow().to_html();
+
+    assert_eq!(parse(&normalised).borrow().to_html(), normalised);
+}
+
+#[test]
+fn descendants_in_tree_order() {
+    let document = parse("<p>a<b>b</b></p><!--c--><i>d</i>");
+
+    let body = body(&document);
+    let names: Vec<String> = body
+        .borrow()
+        .descendants()
+        .map(|node| {
+            let node = node.borrow();
+            match (node.as_element(), node.as_text(), node.as_comment()) {
+                (Some(element), _, _) => element.local_name().to_string(),
+                (_, Some(text), _) => format!("#{}", text),
+                (_, _, Some(data)) => format!("<!--{}-->", data),
+                _ => unreachable!(),
+            }
+        })
+        .collect();
+    assert_eq!(names, ["p", "#a", "b", "#b", "<!--c-->", "i", "#d"]);
+}
+
+#[test]
+fn ancestors_and_siblings() {
+    let document = parse("<p>a<b>b</b>c</p>");
+
+    let body = body(&document);
+    let p = body.borrow().children()[0].clone();
+    let b = p.borrow().children()[1].clone();
+    let ancestors: Vec<_> = b.borrow().ancestors().collect();
+    assert_eq!(ancestors.len(), 4);
+    assert!(Rc::ptr_eq(&ancestors[0], &p));
+    assert!(Rc::ptr_eq(&ancestors[1], &body));
+    assert!(Rc::ptr_eq(&ancestors[3], &document));
+    assert!(Rc::ptr_eq(&b.borrow().parent().unwrap(), &p));
+    assert!(Rc::ptr_eq(&b.borrow().owner_document().unwrap(), &document));
+    assert!(document.borrow().owner_document().is_none());
+    assert!(document.borrow().parent().is_none());
+
+    let preceding = b.borrow().preceding_sibling().unwrap();
+    let following = b.borrow().following_sibling().unwrap();
+    assert_eq!(preceding.borrow().as_text(), Some("a"));
+    assert_eq!(following.borrow().as_text(), Some("c"));
+    assert!(preceding.borrow().preceding_sibling().is_none());
+    assert!(following.borrow().following_sibling().is_none());
+    assert!(document.borrow().following_sibling().is_none());
+}
+
+#[test]
+fn mutation() {
+    let document = parse("<p>a</p><img src=b>");
+
+    let body = body(&document);
+    let p = body.borrow().children()[0].clone();
+    let img = body.borrow().children()[1].clone();
+    let a = Node::create_element("a".into(), Vec::new(), &document, &body);
+    Node::replace_child(&body, &a, &img).unwrap();
+    Node::append_child(&a, &img).unwrap();
+    assert_eq!(body.borrow().to_html(), "<p>a</p><a><img src=\"b\"></a>");
+    assert!(Rc::ptr_eq(&img.borrow().parent().unwrap(), &a));
+
+    Node::insert_before(&body, &a, Some(&p)).unwrap();
+    assert_eq!(body.borrow().to_html(), "<a><img src=\"b\"></a><p>a</p>");
+    Node::insert_before(&body, &a, Some(&a)).unwrap();
+    assert_eq!(body.borrow().to_html(), "<a><img src=\"b\"></a><p>a</p>");
+
+    Node::remove_child(&body, &a).unwrap();
+    assert_eq!(body.borrow().to_html(), "<p>a</p>");
+    assert!(a.borrow().parent().is_none());
+    assert!(Rc::ptr_eq(&a.borrow().owner_document().unwrap(), &document));
+    assert_eq!(Node::remove_child(&body, &a), Err(MutationError::NotFound));
+}
+
+#[test]
+fn mutation_rejects_invalid_trees() {
+    let document = parse("<!DOCTYPE html><p>a</p>");
+
+    let html = document.borrow().children()[1].clone();
+    let body = html.borrow().children()[1].clone();
+    let p = body.borrow().children()[0].clone();
+    let text = p.borrow().children()[0].clone();
+    let doctype = document.borrow().children()[0].clone();
+    assert_eq!(Node::append_child(&p, &body), Err(MutationError::Cycle));
+    assert_eq!(Node::append_child(&p, &p), Err(MutationError::Cycle));
+    assert_eq!(
+        Node::append_child(&text, &p),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(
+        Node::append_child(&document, &text),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(
+        Node::append_child(&document, &p),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(
+        Node::insert_before(&document, &html, Some(&doctype)),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(
+        Node::append_child(&body, &doctype),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(
+        Node::insert_before(&body, &text, Some(&text)),
+        Err(MutationError::NotFound)
+    );
+    assert_eq!(
+        Node::append_child(&p, &Node::create_document()),
+        Err(MutationError::InvalidHierarchy)
+    );
+    assert_eq!(Node::replace_child(&document, &p, &html), Ok(()));
+    assert_eq!(document.borrow().to_html(), "<!DOCTYPE html><p>a</p>");
+}
+
+#[test]
+fn mutation_rejects_a_template_inside_its_contents() {
+    let document = parse("<template><p>a</p></template>");
+
+    let head = document.borrow().children()[0].borrow().children()[0].clone();
+    let template = head.borrow().children()[0].clone();
+    let contents = template.borrow().template_contents().unwrap().clone();
+    let p = contents.borrow().children()[0].clone();
+    assert_eq!(
+        Node::append_child(&contents, &template),
+        Err(MutationError::Cycle)
+    );
+    assert_eq!(Node::append_child(&p, &template), Err(MutationError::Cycle));
+    assert_eq!(
+        Node::append_child(&p, &Node::clone_element(&template)),
+        Ok(())
+    );
+    assert_eq!(template.borrow().to_html(), "<p>a<template></template></p>");
+}
+
+#[test]
+fn adopt() {
+    let document = parse("<p>a<b>b</b></p>");
+    let other_document = Node::create_document();
+
+    let p = body(&document).borrow().children()[0].clone();
+    let other_body =
+        Node::create_element("body".into(), Vec::new(), &other_document, &other_document);
+    Node::append_child(&other_body, &p).unwrap();
+
+    assert!(body(&document).borrow().children().is_empty());
+    let in_other_document = |node: &Rc<RefCell<Node>>| {
+        Rc::ptr_eq(&node.borrow().owner_document().unwrap(), &other_document)
+    };
+    assert!(in_other_document(&p));
+    assert!(p
+        .borrow()
+        .descendants()
+        .all(|node| in_other_document(&node)));
+
+    Node::adopt(&document, &p).unwrap();
+    assert!(other_body.borrow().children().is_empty());
+    assert!(p.borrow().parent().is_none());
+    assert!(Rc::ptr_eq(&p.borrow().owner_document().unwrap(), &document));
+    assert_eq!(
+        Node::adopt(&p, &other_body),
+        Err(MutationError::InvalidHierarchy)
+    );
+}
+
+fn body(document: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    document.borrow().children()[0].borrow().children()[1].clone()
+}
+
+fn parse(input: &str) -> Rc<RefCell<Node>> {
+    let mut parser = Parser::new(TokenizerImpl::new(input.into(), Ignore), Ignore);
+    parser.parse().unwrap()
+}
@@ -0,0 +1,57 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::parser::error::TreeConstructionError;
+use crate::tokenizer::error::ParseError;
+use crate::tokenizer::span::Spanned;
+
+pub type Result<T> = std::result::Result<T, Spanned<Error>>;
+
+/// Any parse error, whether found by the tokenizer or the tree builder
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    Tokenizer(ParseError),
+    TreeConstruction(TreeConstructionError),
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Tokenizer(error) => Some(error),
+            Error::TreeConstruction(error) => Some(error),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Tokenizer(error) => Display::fmt(error, f),
+            Error::TreeConstruction(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Tokenizer(error)
+    }
+}
+
+impl From<TreeConstructionError> for Error {
+    fn from(error: TreeConstructionError) -> Self {
+        Error::TreeConstruction(error)
+    }
+}
+
+impl PartialEq<ParseError> for Error {
+    fn eq(&self, other: &ParseError) -> bool {
+        matches!(self, Error::Tokenizer(error) if error == other)
+    }
+}
+
+impl PartialEq<TreeConstructionError> for Error {
+    fn eq(&self, other: &TreeConstructionError) -> bool {
+        matches!(self, Error::TreeConstruction(error) if error == other)
+    }
+}
@@ -1,3 +1,13 @@
+/// Logs state transitions and emitted tokens, if the `trace` feature is enabled
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        log::trace!($($arg)*);
+    };
+}
+
 pub mod dom;
+pub mod error;
 pub mod parser;
+pub mod sink;
 pub mod tokenizer;
@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// The parse errors found by the tree builder. Unlike the tokenizer's, these aren't given names by
+/// the spec, so the tag name is included where it helps to say what went wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TreeConstructionError {
+    /// A DOCTYPE anywhere other than at the start of the document
+    UnexpectedDoctype,
+    /// The document doesn't start with a DOCTYPE
+    MissingDoctype,
+    /// The DOCTYPE isn't `<!DOCTYPE html>` (or one of the permitted legacy forms)
+    NonConformingDoctype,
+    /// Text (other than whitespace) somewhere it isn't allowed, eg. directly in a `<table>`
+    UnexpectedCharacter,
+    UnexpectedStartTag(String),
+    UnexpectedEndTag(String),
+    /// An end tag which implicitly closes elements which haven't been closed yet
+    EndTagWithUnclosedElements(String),
+    /// The end of the document while elements haven't been closed yet
+    EofWithUnclosedElements,
+    /// Text ended early, eg. in a `<script>` or a `<textarea>` element
+    UnexpectedEof,
+    /// The formatting element's end tag didn't match up with its start tag, so the adoption agency
+    /// algorithm had to rearrange the tree (eg. `<b><i></b></i>`)
+    MisnestedFormattingElement(String),
+    /// Content in a table which is moved to just before the table, since it isn't allowed there
+    FosterParentedContent,
+    /// A comment somewhere only text is allowed, which only happens if the tokenizer ignores
+    /// `Tokenizer::set_state`
+    UnexpectedComment,
+}
+
+impl Error for TreeConstructionError {}
+
+impl Display for TreeConstructionError {
+    //! ```
+    //! use dominoes::parser::error::TreeConstructionError;
+    //! let error = TreeConstructionError::UnexpectedEndTag("p".into());
+    //! assert_eq!(format!("{}", error), "UnexpectedEndTag(\"p\")");
+    //! ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// A fragment can only be parsed in the context of an element, not eg. a comment or a document.
+/// Unlike a parse error, this is a mistake in how the parser was called rather than in the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidContextError;
+
+impl Error for InvalidContextError {}
+
+impl Display for InvalidContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("fragments can only be parsed in the context of an element")
+    }
+}
@@ -0,0 +1,405 @@
+//! Parsing SVG and MathML elements (foreign content), which follow XML-like rules rather than HTML's
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::{Attribute, Namespace, Node};
+use crate::sink::ErrorSink;
+use crate::tokenizer::error::ParseError;
+use crate::tokenizer::TagKind::*;
+use crate::tokenizer::Token::{self, *};
+use crate::tokenizer::{self, Attributes, Tokenizer};
+
+use super::error::TreeConstructionError::*;
+use super::open_elements::is_element_in_namespace;
+use super::Parser;
+
+/// The start tags which break out of foreign content, since they are almost certainly meant to be
+/// HTML (eg. the SVG element was left unclosed)
+const BREAKOUT_ELEMENTS: [&str; 44] = [
+    "b",
+    "big",
+    "blockquote",
+    "body",
+    "br",
+    "center",
+    "code",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "embed",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "hr",
+    "i",
+    "img",
+    "li",
+    "listing",
+    "menu",
+    "meta",
+    "nobr",
+    "ol",
+    "p",
+    "pre",
+    "ruby",
+    "s",
+    "small",
+    "span",
+    "strong",
+    "strike",
+    "sub",
+    "sup",
+    "table",
+    "tt",
+    "u",
+    "ul",
+    "var",
+];
+
+/// The tokenizer lowercases tag names, but some SVG element names are in camel case
+const SVG_TAG_NAME_ADJUSTMENTS: [(&str, &str); 37] = [
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+/// Like `SVG_TAG_NAME_ADJUSTMENTS`, but for attribute names
+const SVG_ATTRIBUTE_ADJUSTMENTS: [(&str, &str); 58] = [
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+/// Like `SVG_ATTRIBUTE_ADJUSTMENTS`, but for MathML
+const MATHML_ATTRIBUTE_ADJUSTMENTS: [(&str, &str); 1] = [("definitionurl", "definitionURL")];
+
+/// The attributes of SVG and MathML elements which are put in a namespace of their own
+const FOREIGN_ATTRIBUTE_NAMESPACES: [(&str, Namespace); 11] = [
+    ("xlink:actuate", Namespace::XLink),
+    ("xlink:arcrole", Namespace::XLink),
+    ("xlink:href", Namespace::XLink),
+    ("xlink:role", Namespace::XLink),
+    ("xlink:show", Namespace::XLink),
+    ("xlink:title", Namespace::XLink),
+    ("xlink:type", Namespace::XLink),
+    ("xml:lang", Namespace::Xml),
+    ("xml:space", Namespace::Xml),
+    ("xmlns", Namespace::XmlNs),
+    ("xmlns:xlink", Namespace::XmlNs),
+];
+
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
+    /// Returns `true` if the token should be handled by the rules for foreign content, rather than
+    /// those of the current insertion mode
+    pub(in crate::parser) fn is_foreign_content(&self, token: &Token) -> bool {
+        let node = match self.adjusted_current_node() {
+            Some(node) => node,
+            None => return false,
+        };
+        if node.borrow().namespace() == Some(&Namespace::Html) {
+            return false;
+        }
+        match token {
+            Character(_) => {
+                !is_mathml_text_integration_point(node) && !is_html_integration_point(node)
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } => {
+                let is_mathml_text = is_mathml_text_integration_point(node)
+                    && tag_name != "mglyph"
+                    && tag_name != "malignmark";
+                let is_svg_in_annotation_xml =
+                    is_element_in_namespace(node, Namespace::MathMl, &["annotation-xml"])
+                        && tag_name == "svg";
+                !is_mathml_text && !is_svg_in_annotation_xml && !is_html_integration_point(node)
+            }
+            EndOfFile => false,
+            _ => true,
+        }
+    }
+
+    pub(in crate::parser) fn handle_in_foreign_content(&mut self, token: Token) {
+        match token {
+            Character('\0') => {
+                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                self.insert_character('\u{FFFD}');
+            }
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Character(ch) => {
+                self.insert_character(ch);
+                self.frameset_ok = false;
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ref attributes,
+                ..
+            } if BREAKOUT_ELEMENTS.contains(&tag_name.as_str())
+                || (tag_name == "font"
+                    && ["color", "face", "size"]
+                        .iter()
+                        .any(|name| attributes.contains(name))) =>
+            {
+                self.break_out_of_foreign_content(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "br" || tag_name == "p" => {
+                self.break_out_of_foreign_content(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                self_closing,
+                ..
+            } => {
+                let namespace = self
+                    .adjusted_current_node()
+                    .and_then(|node| node.borrow().namespace().cloned())
+                    .unwrap();
+                self.insert_foreign_element_for_token(namespace, tag_name, attributes);
+                if self_closing {
+                    self.open_elements.pop();
+                    self.acknowledge_self_closing_flag();
+                }
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } => {
+                let mut index = self.open_elements.len() - 1;
+                if !has_tag_name_ignoring_ascii_case(&self.open_elements[index], tag_name) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name.clone()));
+                }
+                while index > 0 {
+                    if has_tag_name_ignoring_ascii_case(&self.open_elements[index], tag_name) {
+                        self.open_elements.truncate(index);
+                        return;
+                    }
+                    index -= 1;
+                    if self.open_elements[index].borrow().namespace() == Some(&Namespace::Html) {
+                        self.handle_in_current_mode(token);
+                        return;
+                    }
+                }
+            }
+            EndOfFile => {
+                unreachable!("the end of the file is never handled as foreign content");
+            }
+        }
+    }
+
+    /// Closes the open SVG and MathML elements, and reprocesses the token as HTML
+    fn break_out_of_foreign_content(&mut self, token: Token) {
+        self.emit_parse_error(match &token {
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } => UnexpectedStartTag(tag_name.clone()),
+            Tag { tag_name, .. } => UnexpectedEndTag(tag_name.clone()),
+            _ => unreachable!(),
+        });
+        while let Some(node) = self.current_node() {
+            if is_mathml_text_integration_point(node)
+                || is_html_integration_point(node)
+                || node.borrow().namespace() == Some(&Namespace::Html)
+            {
+                break;
+            }
+            self.open_elements.pop();
+        }
+        self.handle_in_current_mode(token);
+    }
+
+    /// Inserts an SVG or MathML element, fixing up the case of its name and attributes (which the
+    /// tokenizer has lowercased)
+    pub(in crate::parser) fn insert_foreign_element_for_token(
+        &mut self,
+        namespace: Namespace,
+        mut tag_name: String,
+        attributes: Attributes,
+    ) -> Rc<RefCell<Node>> {
+        let attribute_adjustments: &[(&str, &str)] = match namespace {
+            Namespace::MathMl => &MATHML_ATTRIBUTE_ADJUSTMENTS,
+            Namespace::Svg => {
+                if let Some(&(_, adjusted)) = SVG_TAG_NAME_ADJUSTMENTS
+                    .iter()
+                    .find(|&&(name, _)| name == tag_name)
+                {
+                    tag_name = String::from(adjusted);
+                }
+                &SVG_ATTRIBUTE_ADJUSTMENTS
+            }
+            _ => &[],
+        };
+        let attributes = attributes
+            .iter()
+            .map(|attribute| foreign_attribute(attribute, attribute_adjustments))
+            .collect();
+        self.insert_foreign_element(namespace, tag_name, attributes)
+    }
+}
+
+/// Fixes up the case of the attribute's name, and puts it in a namespace if it is one of the
+/// `FOREIGN_ATTRIBUTE_NAMESPACES`
+fn foreign_attribute(attribute: &tokenizer::Attribute, adjustments: &[(&str, &str)]) -> Attribute {
+    let name = adjustments
+        .iter()
+        .find(|&&(name, _)| name == attribute.name())
+        .map_or(attribute.name(), |&(_, adjusted)| adjusted);
+    let value = String::from(attribute.value());
+    match FOREIGN_ATTRIBUTE_NAMESPACES
+        .iter()
+        .find(|(foreign_name, _)| *foreign_name == name)
+    {
+        Some((_, namespace)) => {
+            let (prefix, local_name) = match name.split_once(':') {
+                Some((prefix, local_name)) => (Some(String::from(prefix)), local_name),
+                None => (None, name),
+            };
+            Attribute::new_ns(namespace.clone(), prefix, local_name.into(), value)
+        }
+        None => Attribute::new(name.into(), value),
+    }
+}
+
+fn has_tag_name_ignoring_ascii_case(node: &Rc<RefCell<Node>>, tag_name: &str) -> bool {
+    node.borrow()
+        .local_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case(tag_name))
+}
+
+/// MathML elements whose text content is HTML
+fn is_mathml_text_integration_point(node: &Rc<RefCell<Node>>) -> bool {
+    is_element_in_namespace(node, Namespace::MathMl, &["mi", "mo", "mn", "ms", "mtext"])
+}
+
+/// SVG and MathML elements whose content is HTML
+fn is_html_integration_point(node: &Rc<RefCell<Node>>) -> bool {
+    if is_element_in_namespace(node, Namespace::MathMl, &["annotation-xml"]) {
+        let node = node.borrow();
+        let encoding = node.attribute("encoding");
+        return encoding.is_some_and(|encoding| {
+            encoding.eq_ignore_ascii_case("text/html")
+                || encoding.eq_ignore_ascii_case("application/xhtml+xml")
+        });
+    }
+    is_element_in_namespace(node, Namespace::Svg, &["foreignObject", "desc", "title"])
+}
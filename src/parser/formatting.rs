@@ -0,0 +1,276 @@
+//! The list of active formatting elements, which lets formatting elements (eg. `<b>`) carry on
+//! applying across block elements they were misnested with
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::{Attribute, Node};
+use crate::sink::ErrorSink;
+use crate::tokenizer::Tokenizer;
+
+use super::error::TreeConstructionError::*;
+use super::open_elements::{is_element, is_special};
+use super::Parser;
+
+#[derive(Clone, Debug)]
+pub(in crate::parser) enum ActiveFormattingElement {
+    /// Stops formatting elements from leaking into eg. table cells and `<object>` elements
+    Marker,
+    Element(Rc<RefCell<Node>>),
+}
+
+/// What to do once the adoption agency algorithm has finished
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(in crate::parser) enum AdoptionAgencyOutcome {
+    Done,
+    /// There is no formatting element with the end tag's name, so the end tag should be handled
+    /// like any other
+    ActAsAnyOtherEndTag,
+}
+
+impl ActiveFormattingElement {
+    fn element(&self) -> Option<&Rc<RefCell<Node>>> {
+        match self {
+            ActiveFormattingElement::Marker => None,
+            ActiveFormattingElement::Element(element) => Some(element),
+        }
+    }
+}
+
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
+    pub(in crate::parser) fn push_active_formatting_marker(&mut self) {
+        self.active_formatting_elements
+            .push(ActiveFormattingElement::Marker);
+    }
+
+    /// Pushes the element onto the list of active formatting elements. If there are already three
+    /// identical elements after the last marker, the earliest of them is removed (the "Noah's Ark"
+    /// clause).
+    pub(in crate::parser) fn push_active_formatting_element(&mut self, element: Rc<RefCell<Node>>) {
+        let mut identical = self
+            .entries_after_last_marker()
+            .filter(|&(_, entry)| is_identical(entry, &element));
+        let earliest = identical.next().map(|(index, _)| index);
+        if identical.count() >= 2 {
+            self.active_formatting_elements.remove(earliest.unwrap());
+        }
+        self.active_formatting_elements
+            .push(ActiveFormattingElement::Element(element));
+    }
+
+    pub(in crate::parser) fn clear_active_formatting_elements_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting_elements.pop() {
+            if let ActiveFormattingElement::Marker = entry {
+                break;
+            }
+        }
+    }
+
+    /// Returns the last element with the given tag name after the last marker, if there is one
+    pub(in crate::parser) fn active_formatting_element(
+        &self,
+        tag_name: &str,
+    ) -> Option<Rc<RefCell<Node>>> {
+        self.entries_after_last_marker()
+            .rev()
+            .find(|&(_, element)| is_element(element, &[tag_name]))
+            .map(|(_, element)| element.clone())
+    }
+
+    pub(in crate::parser) fn remove_active_formatting_element(
+        &mut self,
+        element: &Rc<RefCell<Node>>,
+    ) {
+        if let Some(index) = self.active_formatting_element_index(element) {
+            self.active_formatting_elements.remove(index);
+        }
+    }
+
+    /// Reopens the formatting elements which were implicitly closed (eg. by `<p>`), so that they
+    /// apply to the content which follows
+    pub(in crate::parser) fn reconstruct_active_formatting_elements(&mut self) {
+        let is_marker_or_open =
+            |parser: &Self, index: usize| match &parser.active_formatting_elements[index] {
+                ActiveFormattingElement::Marker => true,
+                ActiveFormattingElement::Element(element) => parser.is_open(element),
+            };
+
+        let len = self.active_formatting_elements.len();
+        if len == 0 || is_marker_or_open(self, len - 1) {
+            return;
+        }
+        let mut first = len - 1;
+        while first > 0 && !is_marker_or_open(self, first - 1) {
+            first -= 1;
+        }
+        for index in first..len {
+            let entry = self.active_formatting_elements[index].element().unwrap();
+            let element = Node::clone_element(entry);
+            self.insert_element(&element);
+            self.active_formatting_elements[index] = ActiveFormattingElement::Element(element);
+        }
+    }
+
+    /// Handles the end tag of a formatting element (or the start tag of an `<a>` or `<nobr>`
+    /// element while one is still open), closing it even if it is misnested with other elements
+    pub(in crate::parser) fn run_adoption_agency_algorithm(
+        &mut self,
+        subject: &str,
+    ) -> AdoptionAgencyOutcome {
+        if let Some(current_node) = self.current_node() {
+            if is_element(current_node, &[subject])
+                && self.active_formatting_element_index(current_node).is_none()
+            {
+                self.open_elements.pop();
+                return AdoptionAgencyOutcome::Done;
+            }
+        }
+
+        for _ in 0..8 {
+            let formatting_element = match self.active_formatting_element(subject) {
+                Some(element) => element,
+                None => return AdoptionAgencyOutcome::ActAsAnyOtherEndTag,
+            };
+            let formatting_element_stack_index = match self.open_element_index(&formatting_element)
+            {
+                Some(index) => index,
+                None => {
+                    self.emit_parse_error(MisnestedFormattingElement(String::from(subject)));
+                    self.remove_active_formatting_element(&formatting_element);
+                    return AdoptionAgencyOutcome::Done;
+                }
+            };
+            if !self.has_node_in_scope(&formatting_element) {
+                self.emit_parse_error(MisnestedFormattingElement(String::from(subject)));
+                return AdoptionAgencyOutcome::Done;
+            }
+            if !Rc::ptr_eq(self.current_node().unwrap(), &formatting_element) {
+                self.emit_parse_error(MisnestedFormattingElement(String::from(subject)));
+            }
+
+            let furthest_block = self.open_elements[formatting_element_stack_index + 1..]
+                .iter()
+                .find(|node| is_special(node))
+                .cloned();
+            let furthest_block = match furthest_block {
+                Some(furthest_block) => furthest_block,
+                None => {
+                    self.open_elements.truncate(formatting_element_stack_index);
+                    self.remove_active_formatting_element(&formatting_element);
+                    return AdoptionAgencyOutcome::Done;
+                }
+            };
+
+            let common_ancestor = self.open_elements[formatting_element_stack_index - 1].clone();
+            let mut bookmark = self
+                .active_formatting_element_index(&formatting_element)
+                .unwrap();
+            let mut node_index = self.open_element_index(&furthest_block).unwrap();
+            let mut last_node = furthest_block.clone();
+            let mut inner_loop_counter = 0;
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.open_elements[node_index].clone();
+                if Rc::ptr_eq(&node, &formatting_element) {
+                    break;
+                }
+                let mut list_index = self.active_formatting_element_index(&node);
+                if inner_loop_counter > 3 {
+                    if let Some(index) = list_index.take() {
+                        self.active_formatting_elements.remove(index);
+                        if index < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+                let list_index = match list_index {
+                    Some(index) => index,
+                    None => {
+                        self.open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                let new_node = Node::clone_element(&node);
+                self.active_formatting_elements[list_index] =
+                    ActiveFormattingElement::Element(new_node.clone());
+                self.open_elements[node_index] = new_node.clone();
+                if Rc::ptr_eq(&last_node, &furthest_block) {
+                    bookmark = list_index + 1;
+                }
+                Node::append(&new_node, &last_node);
+                last_node = new_node;
+            }
+
+            self.insert_node_at_appropriate_place(&last_node, Some(&common_ancestor));
+
+            let new_element = Node::clone_element(&formatting_element);
+            let children = furthest_block.borrow().children.clone();
+            for child in &children {
+                Node::append(&new_element, child);
+            }
+            Node::append(&furthest_block, &new_element);
+
+            let formatting_element_list_index = self
+                .active_formatting_element_index(&formatting_element)
+                .unwrap();
+            self.active_formatting_elements
+                .remove(formatting_element_list_index);
+            if formatting_element_list_index < bookmark {
+                bookmark -= 1;
+            }
+            self.active_formatting_elements.insert(
+                bookmark,
+                ActiveFormattingElement::Element(new_element.clone()),
+            );
+
+            self.open_elements
+                .retain(|node| !Rc::ptr_eq(node, &formatting_element));
+            let furthest_block_stack_index = self.open_element_index(&furthest_block).unwrap();
+            self.open_elements
+                .insert(furthest_block_stack_index + 1, new_element);
+        }
+        AdoptionAgencyOutcome::Done
+    }
+
+    fn entries_after_last_marker(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (usize, &Rc<RefCell<Node>>)> {
+        let start = self
+            .active_formatting_elements
+            .iter()
+            .rposition(|entry| matches!(entry, ActiveFormattingElement::Marker))
+            .map_or(0, |index| index + 1);
+        self.active_formatting_elements[start..]
+            .iter()
+            .enumerate()
+            .map(move |(index, entry)| (start + index, entry.element().unwrap()))
+    }
+
+    fn active_formatting_element_index(&self, element: &Rc<RefCell<Node>>) -> Option<usize> {
+        self.active_formatting_elements.iter().position(|entry| {
+            entry
+                .element()
+                .is_some_and(|entry| Rc::ptr_eq(entry, element))
+        })
+    }
+
+    fn open_element_index(&self, element: &Rc<RefCell<Node>>) -> Option<usize> {
+        self.open_elements
+            .iter()
+            .position(|node| Rc::ptr_eq(node, element))
+    }
+}
+
+fn is_identical(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> bool {
+    let (a, b) = (a.borrow(), b.borrow());
+    a.namespace() == b.namespace()
+        && a.local_name() == b.local_name()
+        && has_same_attributes(a.attributes().unwrap(), b.attributes().unwrap())
+}
+
+/// Does not care about order
+fn has_same_attributes(a: &[Attribute], b: &[Attribute]) -> bool {
+    a.len() == b.len() && a.iter().all(|attr| b.contains(attr))
+}
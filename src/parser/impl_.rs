@@ -1,10 +1,21 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::{Namespace, Node, QuirksMode};
+use crate::sink::ErrorSink;
+use crate::tokenizer::error::ParseError;
+use crate::tokenizer::TagKind::*;
 use crate::tokenizer::Token::{self, *};
-use crate::tokenizer::Tokenizer;
+use crate::tokenizer::{Attributes, Tokenizer, TokenizerState};
 
+use super::error::TreeConstructionError::{self, *};
+use super::formatting::AdoptionAgencyOutcome;
+use super::open_elements::{is_element, is_special, Scope, HEADINGS};
+use super::quirks::quirks_mode;
 use super::state::InsertionMode::*;
-use super::Parser;
+use super::{html_attribute, Parser};
 
-impl<T: Tokenizer> Parser<T> {
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
     pub(in crate::parser) fn handle_initial(&mut self, token: Token) {
         match token {
             Character('\t')
@@ -14,118 +25,2052 @@ impl<T: Tokenizer> Parser<T> {
             | Character(' ') => {
                 // ignore the token
             }
-            Comment { data: _ } => {
-                unimplemented!();
+            Comment { data } => {
+                Node::create_comment(data, &self.root_node, &self.root_node);
             }
             Doctype {
                 name,
                 public_identifier,
                 system_identifier,
-                force_quirks: _,
+                force_quirks,
             } => {
-                if name != Some("html".into())
+                if name.as_deref() != Some("html")
                     || public_identifier.is_some()
                     || system_identifier
-                        .filter(|id| id != "about:legacy-compat")
+                        .as_deref()
+                        .filter(|&id| id != "about:legacy-compat")
                         .is_some()
                 {
-                    self.emit_anonymous_parse_error();
+                    self.emit_parse_error(NonConformingDoctype);
+                }
+                if !self.iframe_srcdoc {
+                    let quirks_mode = quirks_mode(
+                        name.as_deref(),
+                        public_identifier.as_deref(),
+                        system_identifier.as_deref(),
+                        force_quirks,
+                    );
+                    self.root_node.borrow_mut().set_quirks_mode(quirks_mode);
                 }
-                unimplemented!();
+                Node::create_doctype(
+                    name.unwrap_or_default(),
+                    public_identifier.unwrap_or_default(),
+                    system_identifier.unwrap_or_default(),
+                    &self.root_node,
+                );
+                self.switch_to(BeforeHtml);
             }
             _ => {
-                // TODO: If the document is not an iframe srcdoc document, then this is a parse
-                //       error; set the Document to quirks mode.
+                if !self.iframe_srcdoc {
+                    self.emit_parse_error(MissingDoctype);
+                    self.root_node
+                        .borrow_mut()
+                        .set_quirks_mode(QuirksMode::Quirks);
+                }
                 self.reprocess_in(BeforeHtml);
             }
         }
     }
 
-    pub(in crate::parser) fn handle_before_html(&mut self, _: Token) {
-        unimplemented!();
+    pub(in crate::parser) fn handle_before_html(&mut self, token: Token) {
+        match token {
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Comment { data } => {
+                Node::create_comment(data, &self.root_node, &self.root_node);
+            }
+            Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ') => {
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "html" => {
+                let attributes = attributes.iter().map(html_attribute).collect();
+                let element =
+                    Node::create_element(tag_name, attributes, &self.root_node, &self.root_node);
+                self.open_elements.push(element);
+                self.switch_to(BeforeHead);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if !matches!(tag_name.as_str(), "head" | "body" | "html" | "br") => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                let element = Node::create_element(
+                    String::from("html"),
+                    Vec::new(),
+                    &self.root_node,
+                    &self.root_node,
+                );
+                self.open_elements.push(element);
+                self.reprocess_in(BeforeHead);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_before_head(&mut self, token: Token) {
+        match token {
+            Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ') => {
+                // ignore the token
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "head" => {
+                let head = self.insert_html_element(tag_name, attributes);
+                self.head_element_pointer = Some(head);
+                self.switch_to(InHead);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if !matches!(tag_name.as_str(), "head" | "body" | "html" | "br") => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                let head = self.insert_html_element(String::from("head"), Attributes::new());
+                self.head_element_pointer = Some(head);
+                self.reprocess_in(InHead);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_in_head(&mut self, token: Token) {
+        match token {
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "base" | "basefont" | "bgsound" | "link" | "meta"
+            ) =>
+            {
+                // the input has already been decoded, so a `<meta charset>` makes no difference
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "title" => {
+                self.parse_text_element(tag_name, attributes, TokenizerState::RCDATA);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "noframes" || tag_name == "style" => {
+                self.parse_text_element(tag_name, attributes, TokenizerState::RAWTEXT);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "noscript" => {
+                // scripts are never run, so the scripting flag is never set
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InHeadNoscript);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "script" => {
+                self.parse_text_element(tag_name, attributes, TokenizerState::ScriptData);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "head" => {
+                self.open_elements.pop();
+                self.switch_to(AfterHead);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "template" => {
+                self.insert_html_element(tag_name, attributes);
+                self.push_active_formatting_marker();
+                self.frameset_ok = false;
+                self.switch_to(InTemplate);
+                self.template_insertion_modes.push(InTemplate);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "template" => {
+                if !self.has_open_element(&["template"]) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                self.generate_all_implied_end_tags_thoroughly();
+                if !self.current_node_is(&["template"]) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name));
+                }
+                self.close_template();
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if tag_name == "head" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                // ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if !matches!(tag_name.as_str(), "body" | "html" | "br") => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.open_elements.pop();
+                self.reprocess_in(AfterHead);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_in_head_noscript(&mut self, token: Token) {
+        match token {
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "noscript" => {
+                self.open_elements.pop();
+                self.switch_to(InHead);
+            }
+            Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ')
+            | Comment { .. } => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "basefont" | "bgsound" | "link" | "meta" | "noframes" | "style"
+            ) =>
+            {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if tag_name == "head" || tag_name == "noscript" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                // ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name != "br" => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                match token {
+                    Tag { tag_name, .. } => self.emit_parse_error(UnexpectedStartTag(tag_name)),
+                    EndOfFile => self.emit_parse_error(EofWithUnclosedElements),
+                    _ => self.emit_parse_error(UnexpectedCharacter),
+                }
+                self.open_elements.pop();
+                self.reprocess_in(InHead);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_after_head(&mut self, token: Token) {
+        match token {
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "body" => {
+                self.insert_html_element(tag_name, attributes);
+                self.frameset_ok = false;
+                self.switch_to(InBody);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "frameset" => {
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InFrameset);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "base"
+                    | "basefont"
+                    | "bgsound"
+                    | "link"
+                    | "meta"
+                    | "noframes"
+                    | "script"
+                    | "style"
+                    | "template"
+                    | "title"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                let head = self.head_element_pointer.clone().unwrap();
+                self.open_elements.push(head.clone());
+                self.handle_in_head(token);
+                self.open_elements.retain(|node| !Rc::ptr_eq(node, &head));
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "template" => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if tag_name == "head" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                // ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if !matches!(tag_name.as_str(), "body" | "html" | "br") => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.insert_html_element(String::from("body"), Attributes::new());
+                self.reprocess_in(InBody);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_in_body(&mut self, token: Token) {
+        match token {
+            Character('\0') => {
+                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                // ignore the token
+            }
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_character(ch);
+            }
+            Character(ch) => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_character(ch);
+                self.frameset_ok = false;
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } => {
+                self.handle_in_body_start_tag(tag_name, attributes);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } => {
+                self.handle_in_body_end_tag(tag_name);
+            }
+            EndOfFile => {
+                if !self.template_insertion_modes.is_empty() {
+                    self.handle_in_template(token);
+                    return;
+                }
+                if self.has_unclosed_elements_at_end_of_body() {
+                    self.emit_parse_error(EofWithUnclosedElements);
+                }
+                self.stop_parsing();
+            }
+        }
+    }
+
+    fn handle_in_body_start_tag(&mut self, tag_name: String, attributes: Attributes) {
+        match tag_name.as_str() {
+            "html" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if !self.has_open_element(&["template"]) {
+                    let html = self.open_elements[0].clone();
+                    add_missing_attributes(&html, &attributes);
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style"
+            | "template" | "title" => {
+                self.handle_in_head(self.current_token.clone());
+            }
+            "body" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if self.open_elements.len() > 1
+                    && is_element(&self.open_elements[1], &["body"])
+                    && !self.has_open_element(&["template"])
+                {
+                    self.frameset_ok = false;
+                    let body = self.open_elements[1].clone();
+                    add_missing_attributes(&body, &attributes);
+                }
+            }
+            "frameset" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                if self.open_elements.len() > 1
+                    && is_element(&self.open_elements[1], &["body"])
+                    && self.frameset_ok
+                {
+                    Node::detach(&self.open_elements[1]);
+                    self.open_elements.truncate(1);
+                    self.insert_html_element(tag_name, attributes);
+                    self.switch_to(InFrameset);
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+            | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header"
+            | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search" | "section"
+            | "summary" | "ul" => {
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_element_in_button_scope();
+                if self.current_node_is(&HEADINGS) {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    self.open_elements.pop();
+                }
+                self.insert_html_element(tag_name, attributes);
+            }
+            "pre" | "listing" => {
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+                self.ignore_next_line_feed = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                let has_open_template = self.has_open_element(&["template"]);
+                if self.form_element_pointer.is_some() && !has_open_template {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name));
+                    // ignore the token
+                } else {
+                    self.close_p_element_in_button_scope();
+                    let form = self.insert_html_element(tag_name, attributes);
+                    if !has_open_template {
+                        self.form_element_pointer = Some(form);
+                    }
+                }
+            }
+            "li" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["li"]);
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+            }
+            "dd" | "dt" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["dd", "dt"]);
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+            }
+            "plaintext" => {
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+                self.tokenizer.set_state(TokenizerState::PLAINTEXT);
+            }
+            "button" => {
+                if self.has_element_in_scope(&["button"], Scope::Default) {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    self.generate_implied_end_tags();
+                    self.pop_until(&["button"]);
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                if let Some(a) = self.active_formatting_element("a") {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    self.run_adoption_agency_algorithm("a");
+                    self.remove_active_formatting_element(&a);
+                    self.open_elements.retain(|node| !Rc::ptr_eq(node, &a));
+                }
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_html_element(tag_name, attributes);
+                self.push_active_formatting_element(element);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong"
+            | "tt" | "u" => {
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_html_element(tag_name, attributes);
+                self.push_active_formatting_element(element);
+            }
+            "nobr" => {
+                self.reconstruct_active_formatting_elements();
+                if self.has_element_in_scope(&["nobr"], Scope::Default) {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    self.run_adoption_agency_algorithm("nobr");
+                    self.reconstruct_active_formatting_elements();
+                }
+                let element = self.insert_html_element(tag_name, attributes);
+                self.push_active_formatting_element(element);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+                self.push_active_formatting_marker();
+                self.frameset_ok = false;
+            }
+            "table" => {
+                if self.root_node.borrow().quirks_mode() != Some(QuirksMode::Quirks) {
+                    self.close_p_element_in_button_scope();
+                }
+                self.insert_html_element(tag_name, attributes);
+                self.frameset_ok = false;
+                self.switch_to(InTable);
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+                self.frameset_ok = false;
+            }
+            "input" => {
+                let is_hidden = attributes
+                    .get("type")
+                    .is_some_and(|kind| kind.eq_ignore_ascii_case("hidden"));
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+                if !is_hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+            }
+            "hr" => {
+                self.close_p_element_in_button_scope();
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+                self.frameset_ok = false;
+            }
+            "image" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if let Tag { tag_name, .. } = &mut self.current_token {
+                    *tag_name = String::from("img");
+                }
+                self.reprocess_in(InBody);
+            }
+            "textarea" => {
+                self.insert_html_element(tag_name, attributes);
+                self.ignore_next_line_feed = true;
+                self.tokenizer.set_state(TokenizerState::RCDATA);
+                self.original_mode = Some(self.mode);
+                self.frameset_ok = false;
+                self.switch_to(Text);
+            }
+            "xmp" => {
+                self.close_p_element_in_button_scope();
+                self.reconstruct_active_formatting_elements();
+                self.frameset_ok = false;
+                self.parse_text_element(tag_name, attributes, TokenizerState::RAWTEXT);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_text_element(tag_name, attributes, TokenizerState::RAWTEXT);
+            }
+            "noembed" => {
+                self.parse_text_element(tag_name, attributes, TokenizerState::RAWTEXT);
+            }
+            "select" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+                self.frameset_ok = false;
+                if matches!(
+                    self.mode,
+                    InTable | InCaption | InTableBody | InRow | InCell
+                ) {
+                    self.switch_to(InSelectInTable);
+                } else {
+                    self.switch_to(InSelect);
+                }
+            }
+            "optgroup" | "option" => {
+                if self.current_node_is(&["option"]) {
+                    self.open_elements.pop();
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+            }
+            "rb" | "rtc" => {
+                if self.has_element_in_scope(&["ruby"], Scope::Default) {
+                    self.generate_implied_end_tags();
+                    if !self.current_node_is(&["ruby"]) {
+                        self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    }
+                }
+                self.insert_html_element(tag_name, attributes);
+            }
+            "rp" | "rt" => {
+                if self.has_element_in_scope(&["ruby"], Scope::Default) {
+                    self.generate_implied_end_tags_except(&["rtc"]);
+                    if !self.current_node_is(&["ruby", "rtc"]) {
+                        self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                    }
+                }
+                self.insert_html_element(tag_name, attributes);
+            }
+            "math" | "svg" => {
+                let namespace = if tag_name == "math" {
+                    Namespace::MathMl
+                } else {
+                    Namespace::Svg
+                };
+                self.reconstruct_active_formatting_elements();
+                self.insert_foreign_element_for_token(namespace, tag_name, attributes);
+                if let Tag {
+                    self_closing: true, ..
+                } = self.current_token
+                {
+                    self.open_elements.pop();
+                    self.acknowledge_self_closing_flag();
+                }
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th"
+            | "thead" | "tr" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_html_element(tag_name, attributes);
+            }
+        }
+    }
+
+    fn handle_in_body_end_tag(&mut self, tag_name: String) {
+        match tag_name.as_str() {
+            "template" => {
+                self.handle_in_head(self.current_token.clone());
+            }
+            "body" | "html" => {
+                if !self.has_element_in_scope(&["body"], Scope::Default) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                if self.has_unclosed_elements_at_end_of_body() {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name.clone()));
+                }
+                if tag_name == "body" {
+                    self.switch_to(AfterBody);
+                } else {
+                    self.reprocess_in(AfterBody);
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+            | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+            | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol" | "pre"
+            | "search" | "section" | "summary" | "ul" => {
+                if self.has_element_in_scope(&[tag_name.as_str()], Scope::Default) {
+                    self.close_element(&tag_name);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            "form" => {
+                if self.has_open_element(&["template"]) {
+                    if self.has_element_in_scope(&["form"], Scope::Default) {
+                        self.close_element("form");
+                    } else {
+                        self.emit_parse_error(UnexpectedEndTag(tag_name));
+                        // ignore the token
+                    }
+                    return;
+                }
+                let form = self.form_element_pointer.take();
+                let form = match form {
+                    Some(form) if self.has_node_in_scope(&form) => form,
+                    _ => {
+                        self.emit_parse_error(UnexpectedEndTag(tag_name));
+                        // ignore the token
+                        return;
+                    }
+                };
+                self.generate_implied_end_tags();
+                if !Rc::ptr_eq(self.current_node().unwrap(), &form) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name));
+                }
+                self.open_elements.retain(|node| !Rc::ptr_eq(node, &form));
+            }
+            "p" => {
+                if !self.has_element_in_scope(&["p"], Scope::Button) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name.clone()));
+                    self.insert_html_element(tag_name, Attributes::new());
+                }
+                self.close_element("p");
+            }
+            "li" => {
+                if self.has_element_in_scope(&["li"], Scope::ListItem) {
+                    self.close_element("li");
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            "dd" | "dt" => {
+                if self.has_element_in_scope(&[tag_name.as_str()], Scope::Default) {
+                    self.close_element(&tag_name);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if !self.has_element_in_scope(&HEADINGS, Scope::Default) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                self.generate_implied_end_tags();
+                if !self.current_node_is(&[tag_name.as_str()]) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name));
+                }
+                self.pop_until(&HEADINGS);
+            }
+            "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s" | "small"
+            | "strike" | "strong" | "tt" | "u" => {
+                if self.run_adoption_agency_algorithm(&tag_name)
+                    == AdoptionAgencyOutcome::ActAsAnyOtherEndTag
+                {
+                    self.handle_in_body_any_other_end_tag(tag_name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if self.has_element_in_scope(&[tag_name.as_str()], Scope::Default) {
+                    self.close_element(&tag_name);
+                    self.clear_active_formatting_elements_to_last_marker();
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            "br" => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name.clone()));
+                self.handle_in_body_start_tag(tag_name, Attributes::new());
+            }
+            _ => {
+                self.handle_in_body_any_other_end_tag(tag_name);
+            }
+        }
+    }
+
+    fn handle_in_body_any_other_end_tag(&mut self, tag_name: String) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index].clone();
+            if is_element(&node, &[tag_name.as_str()]) {
+                self.generate_implied_end_tags_except(&[tag_name.as_str()]);
+                if !Rc::ptr_eq(self.current_node().unwrap(), &node) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name));
+                }
+                self.open_elements.truncate(index);
+                return;
+            }
+            if is_special(&node) {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+                return;
+            }
+        }
+    }
+
+    /// Closes the open list item (eg. `<li>`) before opening another one
+    fn close_list_item(&mut self, list_items: &[&str]) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index].clone();
+            if let Some(&tag_name) = list_items.iter().find(|&&name| is_element(&node, &[name])) {
+                self.generate_implied_end_tags_except(&[tag_name]);
+                if !self.current_node_is(&[tag_name]) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(String::from(tag_name)));
+                }
+                self.pop_until(&[tag_name]);
+                return;
+            }
+            if is_special(&node) && !is_element(&node, &["address", "div", "p"]) {
+                return;
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_text(&mut self, token: Token) {
+        match token {
+            Character(ch) => {
+                self.insert_character(ch);
+            }
+            EndOfFile => {
+                self.emit_parse_error(UnexpectedEof);
+                self.open_elements.pop();
+                let original_mode = self.original_mode.take().unwrap();
+                self.reprocess_in(original_mode);
+            }
+            Tag { kind: End, .. } => {
+                // scripts are never run, so `</script>` is the same as any other end tag
+                self.open_elements.pop();
+                let original_mode = self.original_mode.take().unwrap();
+                self.switch_to(original_mode);
+            }
+            token => {
+                // a tokenizer which ignores `set_state` can emit anything here, so the element is
+                // closed as if the end of its text had been reached
+                self.emit_parse_error(unexpected(&token));
+                self.open_elements.pop();
+                let original_mode = self.original_mode.take().unwrap();
+                self.reprocess_in(original_mode);
+            }
+        }
+    }
+
+    pub(in crate::parser) fn handle_in_table(&mut self, token: Token) {
+        match token {
+            Character(_)
+                if self
+                    .current_node_is(&["table", "tbody", "template", "tfoot", "thead", "tr"]) =>
+            {
+                self.pending_table_character_tokens.clear();
+                self.original_mode = Some(self.mode);
+                self.reprocess_in(InTableText);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "caption" => {
+                self.clear_stack_back_to_table_context();
+                self.push_active_formatting_marker();
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InCaption);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "colgroup" => {
+                self.clear_stack_back_to_table_context();
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InColumnGroup);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "col" => {
+                self.clear_stack_back_to_table_context();
+                self.insert_html_element(String::from("colgroup"), Attributes::new());
+                self.reprocess_in(InColumnGroup);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if matches!(tag_name.as_str(), "tbody" | "tfoot" | "thead") => {
+                self.clear_stack_back_to_table_context();
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InTableBody);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "td" | "th" | "tr") => {
+                self.clear_stack_back_to_table_context();
+                self.insert_html_element(String::from("tbody"), Attributes::new());
+                self.reprocess_in(InTableBody);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if tag_name == "table" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if self.has_element_in_scope(&["table"], Scope::Table) {
+                    self.pop_until(&["table"]);
+                    self.reset_insertion_mode_appropriately();
+                    self.reprocess_current_token = true;
+                }
+                // otherwise ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "table" => {
+                if self.has_element_in_scope(&["table"], Scope::Table) {
+                    self.pop_until(&["table"]);
+                    self.reset_insertion_mode_appropriately();
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "body"
+                    | "caption"
+                    | "col"
+                    | "colgroup"
+                    | "html"
+                    | "tbody"
+                    | "td"
+                    | "tfoot"
+                    | "th"
+                    | "thead"
+                    | "tr"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "style" | "script" | "template") => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "template" => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "input"
+                && attributes
+                    .get("type")
+                    .is_some_and(|kind| kind.eq_ignore_ascii_case("hidden")) =>
+            {
+                self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "form" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name.clone()));
+                if self.has_open_element(&["template"]) || self.form_element_pointer.is_some() {
+                    // ignore the token
+                    return;
+                }
+                let form = self.insert_html_element(tag_name, attributes);
+                self.form_element_pointer = Some(form);
+                self.open_elements.pop();
+            }
+            EndOfFile => {
+                self.handle_in_body(token);
+            }
+            _ => {
+                self.emit_parse_error(FosterParentedContent);
+                self.foster_parent(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_before_head(&mut self, _: Token) {
-        unimplemented!()
+    /// Processes the token using the InBody rules, but with any nodes it inserts which would end up
+    /// inside a table inserted before the table instead
+    fn foster_parent(&mut self, token: Token) {
+        self.foster_parenting = true;
+        self.handle_in_body(token);
+        self.foster_parenting = false;
     }
 
-    pub(in crate::parser) fn handle_in_head(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_table_text(&mut self, token: Token) {
+        match token {
+            Character('\0') => {
+                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                // ignore the token
+            }
+            Character(ch) => {
+                self.pending_table_character_tokens.push(ch);
+            }
+            _ => {
+                let pending = std::mem::take(&mut self.pending_table_character_tokens);
+                if pending.chars().all(is_whitespace) {
+                    pending.chars().for_each(|ch| self.insert_character(ch));
+                } else {
+                    self.emit_parse_error(FosterParentedContent);
+                    pending
+                        .chars()
+                        .for_each(|ch| self.foster_parent(Character(ch)));
+                }
+                let original_mode = self.original_mode.take().unwrap();
+                self.reprocess_in(original_mode);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_head_noscript(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_caption(&mut self, token: Token) {
+        match token {
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "caption" => {
+                if self.has_element_in_scope(&["caption"], Scope::Table) {
+                    self.close_caption();
+                    self.switch_to(InTable);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+            ) =>
+            {
+                self.close_caption_and_reprocess(&token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "table" => {
+                self.close_caption_and_reprocess(&token);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "body"
+                    | "col"
+                    | "colgroup"
+                    | "html"
+                    | "tbody"
+                    | "td"
+                    | "tfoot"
+                    | "th"
+                    | "thead"
+                    | "tr"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.handle_in_body(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_after_head(&mut self, _: Token) {
-        unimplemented!()
+    /// Closes the caption, if there is one in table scope, and reprocesses the token in the
+    /// InTable insertion mode
+    fn close_caption_and_reprocess(&mut self, token: &Token) {
+        if self.has_element_in_scope(&["caption"], Scope::Table) {
+            self.close_caption();
+            self.reprocess_in(InTable);
+        } else {
+            self.emit_parse_error(unexpected(token));
+            // ignore the token
+        }
     }
 
-    pub(in crate::parser) fn handle_in_body(&mut self, _: Token) {
-        unimplemented!()
+    fn close_caption(&mut self) {
+        self.generate_implied_end_tags();
+        if !self.current_node_is(&["caption"]) {
+            self.emit_parse_error(EndTagWithUnclosedElements(String::from("caption")));
+        }
+        self.pop_until(&["caption"]);
+        self.clear_active_formatting_elements_to_last_marker();
     }
 
-    pub(in crate::parser) fn handle_text(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_column_group(&mut self, token: Token) {
+        match token {
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "col" => {
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "colgroup" => {
+                if self.current_node_is(&["colgroup"]) {
+                    self.open_elements.pop();
+                    self.switch_to(InTable);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "col" => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            Tag { ref tag_name, .. } if tag_name == "template" => {
+                self.handle_in_head(token);
+            }
+            EndOfFile => {
+                self.handle_in_body(token);
+            }
+            _ => {
+                if self.current_node_is(&["colgroup"]) {
+                    self.open_elements.pop();
+                    self.reprocess_in(InTable);
+                } else {
+                    self.emit_parse_error(unexpected(&token));
+                    // ignore the token
+                }
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_table(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_table_body(&mut self, token: Token) {
+        match token {
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "tr" => {
+                self.clear_stack_back_to_table_body_context();
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InRow);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "td" | "th") => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                self.clear_stack_back_to_table_body_context();
+                self.insert_html_element(String::from("tr"), Attributes::new());
+                self.reprocess_in(InRow);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if self.has_element_in_scope(&[tag_name.as_str()], Scope::Table) {
+                    self.clear_stack_back_to_table_body_context();
+                    self.open_elements.pop();
+                    self.switch_to(InTable);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind, ref tag_name, ..
+            } if (kind == Start
+                && matches!(
+                    tag_name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead"
+                ))
+                || (kind == End && tag_name == "table") =>
+            {
+                if self.has_element_in_scope(&["tbody", "tfoot", "thead"], Scope::Table) {
+                    self.clear_stack_back_to_table_body_context();
+                    self.open_elements.pop();
+                    self.reprocess_in(InTable);
+                } else {
+                    self.emit_parse_error(unexpected(&token));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.handle_in_table(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_table_text(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_row(&mut self, token: Token) {
+        match token {
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if matches!(tag_name.as_str(), "td" | "th") => {
+                self.clear_stack_back_to_table_row_context();
+                self.insert_html_element(tag_name, attributes);
+                self.switch_to(InCell);
+                self.push_active_formatting_marker();
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "tr" => {
+                if self.has_element_in_scope(&["tr"], Scope::Table) {
+                    self.clear_stack_back_to_table_row_context();
+                    self.open_elements.pop();
+                    self.switch_to(InTableBody);
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind, ref tag_name, ..
+            } if (kind == Start
+                && matches!(
+                    tag_name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ))
+                || (kind == End && tag_name == "table") =>
+            {
+                if self.has_element_in_scope(&["tr"], Scope::Table) {
+                    self.clear_stack_back_to_table_row_context();
+                    self.open_elements.pop();
+                    self.reprocess_in(InTableBody);
+                } else {
+                    self.emit_parse_error(unexpected(&token));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if !self.has_element_in_scope(&[tag_name.as_str()], Scope::Table) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                } else if self.has_element_in_scope(&["tr"], Scope::Table) {
+                    self.clear_stack_back_to_table_row_context();
+                    self.open_elements.pop();
+                    self.reprocess_in(InTableBody);
+                }
+                // otherwise ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            _ => {
+                self.handle_in_table(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_caption(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_cell(&mut self, token: Token) {
+        match token {
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "td" | "th") => {
+                if !self.has_element_in_scope(&[tag_name.as_str()], Scope::Table) {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                self.generate_implied_end_tags();
+                if !self.current_node_is(&[tag_name.as_str()]) {
+                    self.emit_parse_error(EndTagWithUnclosedElements(tag_name.clone()));
+                }
+                self.pop_until(&[tag_name.as_str()]);
+                self.clear_active_formatting_elements_to_last_marker();
+                self.switch_to(InRow);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+            ) =>
+            {
+                if self.has_element_in_scope(&["td", "th"], Scope::Table) {
+                    self.close_cell();
+                    self.reprocess_current_token = true;
+                } else {
+                    self.emit_parse_error(UnexpectedStartTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "body" | "caption" | "col" | "colgroup" | "html"
+            ) =>
+            {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "table" | "tbody" | "tfoot" | "thead" | "tr"
+            ) =>
+            {
+                if self.has_element_in_scope(&[tag_name.as_str()], Scope::Table) {
+                    self.close_cell();
+                    self.reprocess_current_token = true;
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            _ => {
+                self.handle_in_body(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_column_group(&mut self, _: Token) {
-        unimplemented!()
+    /// Closes the open `<td>` or `<th>` element, and switches back to the InRow insertion mode
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags();
+        if !self.current_node_is(&["td", "th"]) {
+            self.emit_parse_error(EndTagWithUnclosedElements(String::from("td")));
+        }
+        self.pop_until(&["td", "th"]);
+        self.clear_active_formatting_elements_to_last_marker();
+        self.switch_to(InRow);
     }
 
-    pub(in crate::parser) fn handle_in_table_body(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_select(&mut self, token: Token) {
+        match token {
+            Character('\0') => {
+                self.emit_parse_error(ParseError::UnexpectedNullCharacter);
+                // ignore the token
+            }
+            Character(ch) => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "option" => {
+                if self.current_node_is(&["option"]) {
+                    self.open_elements.pop();
+                }
+                self.insert_html_element(tag_name, attributes);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if matches!(tag_name.as_str(), "optgroup" | "hr") => {
+                if self.current_node_is(&["option"]) {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(&["optgroup"]) {
+                    self.open_elements.pop();
+                }
+                let is_hr = tag_name == "hr";
+                self.insert_html_element(tag_name, attributes);
+                if is_hr {
+                    self.open_elements.pop();
+                    self.acknowledge_self_closing_flag();
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "optgroup" => {
+                let len = self.open_elements.len();
+                if self.current_node_is(&["option"])
+                    && len > 1
+                    && is_element(&self.open_elements[len - 2], &["optgroup"])
+                {
+                    self.open_elements.pop();
+                }
+                if self.current_node_is(&["optgroup"]) {
+                    self.open_elements.pop();
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "option" => {
+                if self.current_node_is(&["option"]) {
+                    self.open_elements.pop();
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "select" => {
+                if self.has_element_in_scope(&["select"], Scope::Select) {
+                    self.pop_until(&["select"]);
+                    self.reset_insertion_mode_appropriately();
+                } else {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                }
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if tag_name == "select" => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if self.has_element_in_scope(&["select"], Scope::Select) {
+                    self.pop_until(&["select"]);
+                    self.reset_insertion_mode_appropriately();
+                }
+                // otherwise ignore the token
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "input" | "keygen" | "textarea") => {
+                self.emit_parse_error(UnexpectedStartTag(tag_name));
+                if self.has_element_in_scope(&["select"], Scope::Select) {
+                    self.pop_until(&["select"]);
+                    self.reset_insertion_mode_appropriately();
+                    self.reprocess_current_token = true;
+                }
+                // otherwise ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(tag_name.as_str(), "script" | "template") => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "template" => {
+                self.handle_in_head(token);
+            }
+            EndOfFile => {
+                self.handle_in_body(token);
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                // ignore the token
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_row(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_select_in_table(&mut self, token: Token) {
+        match token {
+            Tag {
+                kind, ref tag_name, ..
+            } if matches!(
+                tag_name.as_str(),
+                "caption" | "table" | "tbody" | "tfoot" | "thead" | "tr" | "td" | "th"
+            ) =>
+            {
+                self.emit_parse_error(unexpected(&token));
+                if kind == End && !self.has_element_in_scope(&[tag_name.as_str()], Scope::Table) {
+                    // ignore the token
+                    return;
+                }
+                self.pop_until(&["select"]);
+                self.reset_insertion_mode_appropriately();
+                self.reprocess_current_token = true;
+            }
+            _ => {
+                self.handle_in_select(token);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_cell(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_template(&mut self, token: Token) {
+        match token {
+            Character(_) | Comment { .. } | Doctype { .. } => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if matches!(
+                tag_name.as_str(),
+                "base"
+                    | "basefont"
+                    | "bgsound"
+                    | "link"
+                    | "meta"
+                    | "noframes"
+                    | "script"
+                    | "style"
+                    | "template"
+                    | "title"
+            ) =>
+            {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "template" => {
+                self.handle_in_head(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                ..
+            } => {
+                let next_mode = match tag_name.as_str() {
+                    "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => InTable,
+                    "col" => InColumnGroup,
+                    "tr" => InTableBody,
+                    "td" | "th" => InRow,
+                    _ => InBody,
+                };
+                self.template_insertion_modes.pop();
+                self.template_insertion_modes.push(next_mode);
+                self.reprocess_in(next_mode);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } => {
+                self.emit_parse_error(UnexpectedEndTag(tag_name));
+                // ignore the token
+            }
+            EndOfFile => {
+                if !self.has_open_element(&["template"]) {
+                    self.stop_parsing();
+                    return;
+                }
+                self.emit_parse_error(EofWithUnclosedElements);
+                self.close_template();
+                self.reprocess_current_token = true;
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_select(&mut self, _: Token) {
-        unimplemented!()
+    /// Pops the `<template>` element (and anything still open inside it), along with its
+    /// formatting elements and insertion mode
+    fn close_template(&mut self) {
+        self.pop_until(&["template"]);
+        self.clear_active_formatting_elements_to_last_marker();
+        self.template_insertion_modes.pop();
+        self.reset_insertion_mode_appropriately();
     }
 
-    pub(in crate::parser) fn handle_in_select_in_table(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_after_body(&mut self, token: Token) {
+        match token {
+            Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ') => {
+                self.handle_in_body(token);
+            }
+            Comment { data } => {
+                let html = self.open_elements[0].clone();
+                Node::create_comment(data, &self.root_node, &html);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "html" => {
+                if self.context_element.is_some() {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                self.switch_to(AfterAfterBody);
+            }
+            EndOfFile => {
+                self.stop_parsing();
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                self.reprocess_in(InBody);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_template(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_in_frameset(&mut self, token: Token) {
+        match token {
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "frameset" => {
+                self.insert_html_element(tag_name, attributes);
+            }
+            Tag {
+                kind: End,
+                tag_name,
+                ..
+            } if tag_name == "frameset" => {
+                if self.open_elements.len() == 1 {
+                    self.emit_parse_error(UnexpectedEndTag(tag_name));
+                    // ignore the token
+                    return;
+                }
+                self.open_elements.pop();
+                if !self.current_node_is(&["frameset"]) {
+                    self.switch_to(AfterFrameset);
+                }
+            }
+            Tag {
+                kind: Start,
+                tag_name,
+                attributes,
+                ..
+            } if tag_name == "frame" => {
+                self.insert_html_element(tag_name, attributes);
+                self.open_elements.pop();
+                self.acknowledge_self_closing_flag();
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "noframes" => {
+                self.handle_in_head(token);
+            }
+            EndOfFile => {
+                if self.open_elements.len() > 1 {
+                    self.emit_parse_error(EofWithUnclosedElements);
+                }
+                self.stop_parsing();
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                // ignore the token
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_after_body(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_after_frameset(&mut self, token: Token) {
+        match token {
+            Character(ch @ '\t')
+            | Character(ch @ '\n')
+            | Character(ch @ '\u{000C}')
+            | Character(ch @ '\r')
+            | Character(ch @ ' ') => {
+                self.insert_character(ch);
+            }
+            Comment { data } => {
+                self.insert_comment(data);
+            }
+            Doctype { .. } => {
+                self.emit_parse_error(UnexpectedDoctype);
+                // ignore the token
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: End,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.switch_to(AfterAfterFrameset);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "noframes" => {
+                self.handle_in_head(token);
+            }
+            EndOfFile => {
+                self.stop_parsing();
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                // ignore the token
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_in_frameset(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_after_after_body(&mut self, token: Token) {
+        match token {
+            Comment { data } => {
+                Node::create_comment(data, &self.root_node, &self.root_node);
+            }
+            Doctype { .. }
+            | Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ') => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            EndOfFile => {
+                self.stop_parsing();
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                self.reprocess_in(InBody);
+            }
+        }
     }
 
-    pub(in crate::parser) fn handle_after_frameset(&mut self, _: Token) {
-        unimplemented!()
+    pub(in crate::parser) fn handle_after_after_frameset(&mut self, token: Token) {
+        match token {
+            Comment { data } => {
+                Node::create_comment(data, &self.root_node, &self.root_node);
+            }
+            Doctype { .. }
+            | Character('\t')
+            | Character('\n')
+            | Character('\u{000C}')
+            | Character('\r')
+            | Character(' ') => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "html" => {
+                self.handle_in_body(token);
+            }
+            Tag {
+                kind: Start,
+                ref tag_name,
+                ..
+            } if tag_name == "noframes" => {
+                self.handle_in_head(token);
+            }
+            EndOfFile => {
+                self.stop_parsing();
+            }
+            _ => {
+                self.emit_parse_error(unexpected(&token));
+                // ignore the token
+            }
+        }
     }
+}
 
-    pub(in crate::parser) fn handle_after_after_body(&mut self, _: Token) {
-        unimplemented!()
+/// Adds the attributes the element doesn't already have (for a duplicate `<html>` or `<body>` start
+/// tag)
+fn add_missing_attributes(element: &Rc<RefCell<Node>>, attributes: &Attributes) {
+    let mut element = element.borrow_mut();
+    let element_attributes = element.attributes_mut().unwrap();
+    for attribute in attributes {
+        if !element_attributes
+            .iter()
+            .any(|attr| attr.local_name() == attribute.name())
+        {
+            element_attributes.push(html_attribute(attribute));
+        }
     }
+}
 
-    pub(in crate::parser) fn handle_after_after_frameset(&mut self, _: Token) {
-        unimplemented!()
+/// The parse error for a token which isn't allowed in the current insertion mode
+fn unexpected(token: &Token) -> TreeConstructionError {
+    match token {
+        Character(_) => UnexpectedCharacter,
+        Doctype { .. } => UnexpectedDoctype,
+        Tag {
+            kind: Start,
+            tag_name,
+            ..
+        } => UnexpectedStartTag(tag_name.clone()),
+        Tag {
+            kind: End,
+            tag_name,
+            ..
+        } => UnexpectedEndTag(tag_name.clone()),
+        EndOfFile => EofWithUnclosedElements,
+        Comment { .. } => UnexpectedComment,
     }
 }
+
+fn is_whitespace(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\u{000C}' | '\r' | ' ')
+}
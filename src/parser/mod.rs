@@ -1,89 +1,525 @@
 use std::cell::RefCell;
+use std::iter;
 use std::rc::Rc;
 
+use error::InvalidContextError;
+use formatting::ActiveFormattingElement;
+use open_elements::is_element;
 use state::InsertionMode;
 
-use crate::dom::Node;
+use crate::dom::{Attribute, Namespace, Node};
+use crate::error::{Error, Result};
+use crate::sink::{ErrorSink, FailFast, Ignore, Shared};
 use crate::tokenizer::error::ParseError;
-use crate::tokenizer::{Token, Tokenizer};
+use crate::tokenizer::span::{Span, Spanned};
+use crate::tokenizer::{
+    self, Attributes, TagKind, Token, Tokenizer, TokenizerImpl, TokenizerState,
+};
 
+pub mod error;
+
+mod foreign;
+mod formatting;
 mod impl_;
+mod open_elements;
+mod quirks;
 mod state;
 
 #[cfg(test)]
 mod tests;
 
-pub struct Parser<T: Tokenizer> {
+pub struct Parser<T: Tokenizer, S: ErrorSink> {
     tokenizer: T,
+    error_sink: S,
+    /// The parse error at which an error sink (either this one or the tokenizer's) stopped parsing
+    aborted: Option<Spanned<Error>>,
     root_node: Rc<RefCell<Node>>,
+    /// The element whose contents are being parsed, if this is parsing a fragment
+    context_element: Option<Rc<RefCell<Node>>>,
+    /// An iframe srcdoc document is never in quirks mode, and doesn't need a DOCTYPE
+    iframe_srcdoc: bool,
     mode: InsertionMode,
+    /// The insertion mode to return to after the Text insertion mode
+    original_mode: Option<InsertionMode>,
+    /// The insertion modes for the contents of the open `<template>` elements
+    template_insertion_modes: Vec<InsertionMode>,
+    open_elements: Vec<Rc<RefCell<Node>>>,
+    active_formatting_elements: Vec<ActiveFormattingElement>,
+    head_element_pointer: Option<Rc<RefCell<Node>>>,
+    form_element_pointer: Option<Rc<RefCell<Node>>>,
+    /// Set after a `<pre>`, `<listing>` or `<textarea>` start tag, since a newline straight after
+    /// one is ignored
+    ignore_next_line_feed: bool,
+    /// Cleared once anything has been parsed which shouldn't be thrown away by a `<frameset>`
+    frameset_ok: bool,
+    /// Set while content which has been misplaced inside a table is being inserted, so it ends up
+    /// just before the table
+    foster_parenting: bool,
+    /// The characters found directly inside a table, which are only foster parented if they
+    /// aren't all whitespace
+    pending_table_character_tokens: String,
     current_token: Token,
+    current_token_span: Span,
     reprocess_current_token: bool,
+    /// Set once the current token's self-closing flag has been acknowledged, which only void
+    /// elements (eg. `<br/>`) may have
+    acknowledged_self_closing: bool,
+}
+
+/// Where a node is to be inserted: before the reference child, or at the end of the parent's
+/// children if there isn't one
+struct InsertionLocation {
+    parent: Rc<RefCell<Node>>,
+    reference_child: Option<Rc<RefCell<Node>>>,
 }
 
-impl<T: Tokenizer> Parser<T> {
-    pub fn new(tokenizer: T) -> Self {
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
+    /// Parse errors found by the tree builder are reported to the `error_sink`. The tokenizer
+    /// reports the errors it finds to its own sink, unless it shares this one (see
+    /// `with_error_sink`).
+    pub fn new(tokenizer: T, error_sink: S) -> Self {
         Self {
             tokenizer,
+            error_sink,
+            aborted: None,
             root_node: Node::create_document(),
+            context_element: None,
+            iframe_srcdoc: false,
             mode: InsertionMode::Initial,
+            original_mode: None,
+            template_insertion_modes: Vec::new(),
+            open_elements: Vec::new(),
+            active_formatting_elements: Vec::new(),
+            head_element_pointer: None,
+            form_element_pointer: None,
+            ignore_next_line_feed: false,
+            frameset_ok: true,
+            foster_parenting: false,
+            pending_table_character_tokens: String::new(),
             current_token: Token::EndOfFile,
+            current_token_span: Span::default(),
             reprocess_current_token: false,
+            acknowledged_self_closing: false,
         }
     }
 
-    pub fn parse(&mut self) -> Rc<RefCell<Node>> {
+    /// Whether the input is the `srcdoc` attribute of an `<iframe>` element
+    pub fn set_iframe_srcdoc(&mut self, iframe_srcdoc: bool) {
+        self.iframe_srcdoc = iframe_srcdoc;
+    }
+
+    /// Returns the parse error at which an error sink stopped parsing, if any
+    pub fn parse(&mut self) -> Result<Rc<RefCell<Node>>> {
         while self.do_some_work() {}
-        self.root_node.clone()
+        match self.aborted.take() {
+            Some(error) => Err(error),
+            None => Ok(self.root_node.clone()),
+        }
     }
 
+    /// Like [`Parser::parse_fragment`], but reports parse errors to this parser's error sinks.
+    /// Returns the parse error at which an error sink stopped parsing, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the context isn't an element
+    pub fn parse_in_context(
+        &mut self,
+        context_element: &Rc<RefCell<Node>>,
+    ) -> Result<Vec<Rc<RefCell<Node>>>> {
+        {
+            let context = context_element.borrow();
+            let tag_name = match context.namespace() {
+                Some(Namespace::Html) => context.local_name().unwrap(),
+                Some(_) => "",
+                None => panic!("Expected an element but was: {:?}", context.data),
+            };
+            let state = match tag_name {
+                "title" | "textarea" => Some(TokenizerState::RCDATA),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                    Some(TokenizerState::RAWTEXT)
+                }
+                "script" => Some(TokenizerState::ScriptData),
+                "plaintext" => Some(TokenizerState::PLAINTEXT),
+                // scripts are never run, so the content of a `<noscript>` is parsed as markup
+                _ => None,
+            };
+            if let Some(state) = state {
+                self.tokenizer.set_state(state);
+            }
+            let document = context.owner_document();
+            if let Some(quirks_mode) = document.and_then(|document| document.borrow().quirks_mode())
+            {
+                self.root_node.borrow_mut().set_quirks_mode(quirks_mode);
+            }
+        }
+
+        let root = Node::create_element(
+            String::from("html"),
+            Vec::new(),
+            &self.root_node,
+            &self.root_node,
+        );
+        self.open_elements.push(root.clone());
+        if is_element(context_element, &["template"]) {
+            self.template_insertion_modes
+                .push(InsertionMode::InTemplate);
+        }
+        self.context_element = Some(context_element.clone());
+        self.reset_insertion_mode_appropriately();
+        let ancestors = context_element.borrow().ancestors();
+        self.form_element_pointer = iter::once(context_element.clone())
+            .chain(ancestors)
+            .find(|node| is_element(node, &["form"]));
+
+        while self.do_some_work() {}
+        if let Some(error) = self.aborted.take() {
+            return Err(error);
+        }
+        let children = root.borrow().children.clone();
+        for child in &children {
+            Node::detach(child);
+        }
+        Ok(children)
+    }
+}
+
+impl Parser<TokenizerImpl<Ignore>, Ignore> {
+    /// Parses the input as the contents of the context element (like setting its `innerHTML`),
+    /// without touching the context element itself. Returns the parsed nodes, which aren't in any
+    /// tree. Fails if the context isn't an element.
+    pub fn parse_fragment(
+        context_element: &Rc<RefCell<Node>>,
+        input: String,
+    ) -> std::result::Result<Vec<Rc<RefCell<Node>>>, InvalidContextError> {
+        if context_element.borrow().as_element().is_none() {
+            return Err(InvalidContextError);
+        }
+        let mut parser = Self::new(TokenizerImpl::new(input, Ignore), Ignore);
+        // neither error sink ever stops parsing
+        Ok(parser.parse_in_context(context_element).unwrap())
+    }
+}
+
+impl<S: ErrorSink> Parser<TokenizerImpl<Shared<S>>, Shared<S>> {
+    /// Creates a parser whose tokenizer reports to the same `error_sink` as the tree builder, so it
+    /// sees every parse error in the order they were found
+    pub fn with_error_sink(input: String, error_sink: S) -> Self {
+        let error_sink = Shared::new(error_sink);
+        Self::new(TokenizerImpl::new(input, error_sink.clone()), error_sink)
+    }
+}
+
+impl Parser<TokenizerImpl<FailFast>, FailFast> {
+    /// Creates a parser which stops at the first parse error (whether found by the tokenizer or the
+    /// tree builder), so `parse` returns it along with its position
+    pub fn strict(input: String) -> Self {
+        Self::new(TokenizerImpl::strict(input), FailFast::new())
+    }
+}
+
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
     pub(crate) fn switch_to(&mut self, next_mode: InsertionMode) {
         if self.mode == next_mode {
             return;
         }
-        println!("Parser::switch_to: {:?} -> {:?}", self.mode, next_mode);
+        trace!("Parser::switch_to: {:?} -> {:?}", self.mode, next_mode);
         self.mode = next_mode;
     }
 
+    pub(in crate::parser) fn acknowledge_self_closing_flag(&mut self) {
+        self.acknowledged_self_closing = true;
+    }
+
     pub(crate) fn reprocess_in(&mut self, next_mode: InsertionMode) {
-        println!("Parser::reprocess_in: {:?} -> {:?}", self.mode, next_mode);
+        trace!("Parser::reprocess_in: {:?} -> {:?}", self.mode, next_mode);
         debug_assert!(!self.reprocess_current_token);
         self.reprocess_current_token = true;
         self.mode = next_mode;
     }
 
+    /// Works out the insertion mode from the stack of open elements, eg. after a table has been
+    /// closed
+    pub(crate) fn reset_insertion_mode_appropriately(&mut self) {
+        for (index, node) in self.open_elements.iter().enumerate().rev() {
+            let last = index == 0;
+            let node = match &self.context_element {
+                Some(context_element) if last => context_element,
+                _ => node,
+            };
+            let node = node.borrow();
+            let tag_name = match node.namespace() {
+                Some(Namespace::Html) => node.local_name().unwrap(),
+                _ => "",
+            };
+            let next_mode = match tag_name {
+                "select" => {
+                    let in_table = self.open_elements[..index]
+                        .iter()
+                        .rev()
+                        .take_while(|ancestor| !is_element(ancestor, &["template"]))
+                        .any(|ancestor| is_element(ancestor, &["table"]));
+                    if in_table {
+                        InsertionMode::InSelectInTable
+                    } else {
+                        InsertionMode::InSelect
+                    }
+                }
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "template" => *self.template_insertion_modes.last().unwrap(),
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "frameset" => InsertionMode::InFrameset,
+                "html" if self.head_element_pointer.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            drop(node);
+            self.switch_to(next_mode);
+            return;
+        }
+    }
+
+    /// Pops everything off the stack of open elements. The tokenizer has already emitted its last
+    /// token by now.
+    fn stop_parsing(&mut self) {
+        self.open_elements.clear();
+    }
+
     /// Returns `true` if there is more work to do
     fn do_some_work(&mut self) -> bool {
-        if let Some(token) = self.next_non_error_token() {
+        if self.aborted.is_some() {
+            return false;
+        }
+        if let Some(token) = self.next_token() {
+            if self.ignore_next_line_feed {
+                self.ignore_next_line_feed = false;
+                if token == Token::Character('\n') {
+                    return true;
+                }
+            }
             self.handle(token);
+            if !self.reprocess_current_token {
+                let self_closing_start_tag = matches!(
+                    self.current_token,
+                    Token::Tag {
+                        kind: TagKind::Start,
+                        self_closing: true,
+                        ..
+                    }
+                );
+                if self_closing_start_tag && !self.acknowledged_self_closing {
+                    self.emit_parse_error(
+                        ParseError::NonVoidHtmlElementStartTagWithTrailingSolidus,
+                    );
+                }
+                self.acknowledged_self_closing = false;
+            }
             true
         } else {
             false
         }
     }
 
-    fn next_non_error_token(&mut self) -> Option<Token> {
-        if !self.reprocess_current_token {
-            loop {
-                match self.tokenizer.next() {
-                    Some(Ok(token)) => {
-                        self.current_token = token;
-                        break;
-                    }
-                    Some(Err(error)) => {
-                        self.emit_parse_error(error);
-                        continue;
-                    }
-                    None => {
-                        return None;
-                    }
+    fn next_token(&mut self) -> Option<Token> {
+        if self.reprocess_current_token {
+            self.reprocess_current_token = false;
+        } else {
+            let namespace = self.adjusted_current_node_namespace();
+            self.tokenizer
+                .set_adjusted_current_node_namespace(namespace);
+            match self.tokenizer.next() {
+                Some(Ok(Spanned { value, span })) => {
+                    self.current_token = value;
+                    self.current_token_span = span;
+                }
+                Some(Err(error)) => {
+                    // the tokenizer's error sink has stopped tokenization
+                    self.aborted = Some(error.map(Error::from));
+                    return None;
+                }
+                None => {
+                    return None;
                 }
             }
         }
         Some(self.current_token.clone())
     }
 
+    /// The adjusted current node is the context element when parsing a fragment with only a single
+    /// element in the stack of open elements, and otherwise the current node
+    fn adjusted_current_node(&self) -> Option<&Rc<RefCell<Node>>> {
+        match &self.context_element {
+            Some(context_element) if self.open_elements.len() == 1 => Some(context_element),
+            _ => self.current_node(),
+        }
+    }
+
+    fn adjusted_current_node_namespace(&self) -> Option<Namespace> {
+        self.adjusted_current_node()
+            .and_then(|node| node.borrow().namespace().cloned())
+    }
+
+    /// The bottommost node in the stack of open elements
+    fn current_node(&self) -> Option<&Rc<RefCell<Node>>> {
+        self.open_elements.last()
+    }
+
+    /// Returns where new nodes should be inserted: the override target if there is one, and
+    /// otherwise the current node. While foster parenting is enabled, content which would end up
+    /// inside a table is inserted just before the table instead. Anything which would end up
+    /// directly inside a `<template>` goes into its contents.
+    fn appropriate_place_for_inserting_a_node(
+        &self,
+        override_target: Option<&Rc<RefCell<Node>>>,
+    ) -> InsertionLocation {
+        let location = self.adjusted_insertion_location(override_target);
+        let template_contents = location.parent.borrow().template_contents().cloned();
+        match template_contents {
+            Some(contents) => InsertionLocation {
+                parent: contents,
+                reference_child: None,
+            },
+            None => location,
+        }
+    }
+
+    fn adjusted_insertion_location(
+        &self,
+        override_target: Option<&Rc<RefCell<Node>>>,
+    ) -> InsertionLocation {
+        let target = override_target
+            .or_else(|| self.current_node())
+            .expect("the stack of open elements is empty");
+        if !self.foster_parenting
+            || !is_element(target, &["table", "tbody", "tfoot", "thead", "tr"])
+        {
+            return InsertionLocation {
+                parent: target.clone(),
+                reference_child: None,
+            };
+        }
+
+        let last_index_of = |tag_name| {
+            self.open_elements
+                .iter()
+                .rposition(|node| is_element(node, &[tag_name]))
+        };
+        let last_template = last_index_of("template");
+        let last_table = match last_index_of("table") {
+            Some(last_table) if last_template.is_none_or(|template| template < last_table) => {
+                last_table
+            }
+            _ => {
+                let parent = last_template.unwrap_or(0);
+                return InsertionLocation {
+                    parent: self.open_elements[parent].clone(),
+                    reference_child: None,
+                };
+            }
+        };
+        let table = &self.open_elements[last_table];
+        let table_parent = table.borrow().parent();
+        match table_parent {
+            Some(parent) => InsertionLocation {
+                parent,
+                reference_child: Some(table.clone()),
+            },
+            None => InsertionLocation {
+                parent: self.open_elements[last_table - 1].clone(),
+                reference_child: None,
+            },
+        }
+    }
+
+    fn insert_node_at_appropriate_place(
+        &mut self,
+        node: &Rc<RefCell<Node>>,
+        override_target: Option<&Rc<RefCell<Node>>>,
+    ) {
+        let location = self.appropriate_place_for_inserting_a_node(override_target);
+        match &location.reference_child {
+            Some(reference_child) => Node::insert(&location.parent, node, reference_child),
+            None => Node::append(&location.parent, node),
+        }
+    }
+
+    /// Inserts an element for the tag token, and pushes it onto the stack of open elements
+    fn insert_html_element(
+        &mut self,
+        tag_name: String,
+        attributes: Attributes,
+    ) -> Rc<RefCell<Node>> {
+        let attributes = attributes.iter().map(html_attribute).collect();
+        self.insert_foreign_element(Namespace::Html, tag_name, attributes)
+    }
+
+    /// Like `insert_html_element`, but for an element in any namespace, whose attributes have
+    /// already been adjusted
+    fn insert_foreign_element(
+        &mut self,
+        namespace: Namespace,
+        tag_name: String,
+        attributes: Vec<Attribute>,
+    ) -> Rc<RefCell<Node>> {
+        let element =
+            Node::create_detached_element(namespace, tag_name, attributes, &self.root_node);
+        self.insert_element(&element);
+        element
+    }
+
+    /// Inserts an element which was created detached, and pushes it onto the stack of open
+    /// elements
+    fn insert_element(&mut self, element: &Rc<RefCell<Node>>) {
+        self.insert_node_at_appropriate_place(element, None);
+        self.open_elements.push(element.clone());
+    }
+
+    fn insert_character(&mut self, ch: char) {
+        let location = self.appropriate_place_for_inserting_a_node(None);
+        let mut buf = [0; 4];
+        Node::insert_text(
+            ch.encode_utf8(&mut buf),
+            &self.root_node,
+            &location.parent,
+            location.reference_child.as_ref(),
+        );
+    }
+
+    fn insert_comment(&mut self, data: String) {
+        let comment = Node::create_detached_comment(data, &self.root_node);
+        self.insert_node_at_appropriate_place(&comment, None);
+    }
+
+    /// The generic raw text and generic RCDATA element parsing algorithms: the element's contents
+    /// are tokenized as text, until its end tag
+    fn parse_text_element(
+        &mut self,
+        tag_name: String,
+        attributes: Attributes,
+        state: TokenizerState,
+    ) {
+        self.insert_html_element(tag_name, attributes);
+        self.tokenizer.set_state(state);
+        self.original_mode = Some(self.mode);
+        self.switch_to(InsertionMode::Text);
+    }
+
     fn handle(&mut self, token: Token) {
+        if self.is_foreign_content(&token) {
+            self.handle_in_foreign_content(token);
+        } else {
+            self.handle_in_current_mode(token);
+        }
+    }
+
+    fn handle_in_current_mode(&mut self, token: Token) {
         match self.mode {
             InsertionMode::Initial => self.handle_initial(token),
             InsertionMode::BeforeHtml => self.handle_before_html(token),
@@ -111,13 +547,17 @@ impl<T: Tokenizer> Parser<T> {
         }
     }
 
-    fn emit_parse_error(&self, error: ParseError) {
-        // TODO: emit the parse error
-        println!("Parser::emit_parse_error: {:?}", error);
+    /// Parse errors found by the tree builder are attributed to the current token
+    fn emit_parse_error(&mut self, error: impl Into<Error>) {
+        let error = Spanned::new(error.into(), self.current_token_span);
+        trace!("Parser::emit_parse_error: {:?}", error);
+        if self.error_sink.report(error.clone()).is_break() {
+            self.aborted = Some(error);
+        }
     }
+}
 
-    fn emit_anonymous_parse_error(&self) {
-        // TODO: work out what kind of parse error it should be
-        println!("Parser::emit_anonymous_parse_error");
-    }
+/// The attribute as it is stored on an HTML element, where no attribute is in a namespace
+fn html_attribute(attribute: &tokenizer::Attribute) -> Attribute {
+    Attribute::new(attribute.name().into(), attribute.value().into())
 }
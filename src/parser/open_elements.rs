@@ -0,0 +1,325 @@
+//! The stack of open elements, and the element categories the tree builder looks for in it
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::{Namespace, Node};
+use crate::sink::ErrorSink;
+use crate::tokenizer::Tokenizer;
+
+use super::error::TreeConstructionError::*;
+use super::Parser;
+
+/// The elements which are given special treatment by the tree builder, eg. they stop the search for
+/// a matching start tag when handling an unknown end tag
+const SPECIAL_ELEMENTS: [&str; 83] = [
+    "address",
+    "applet",
+    "area",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "bgsound",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "iframe",
+    "img",
+    "input",
+    "keygen",
+    "li",
+    "link",
+    "listing",
+    "main",
+    "marquee",
+    "menu",
+    "meta",
+    "nav",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "ol",
+    "p",
+    "param",
+    "plaintext",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "select",
+    "source",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+    "wbr",
+    "xmp",
+];
+
+pub(in crate::parser) const HEADINGS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// The elements which are closed by generating implied end tags
+const IMPLIED_END_TAG_ELEMENTS: [&str; 10] = [
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+/// The elements which are closed by generating all implied end tags thoroughly
+const THOROUGHLY_IMPLIED_END_TAG_ELEMENTS: [&str; 18] = [
+    "caption", "colgroup", "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+    "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+/// The elements which may still be open at the end of the body without it being a parse error
+const ELEMENTS_ALLOWED_OPEN_AT_END_OF_BODY: [&str; 18] = [
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc", "tbody", "td", "tfoot",
+    "th", "thead", "tr", "body", "html",
+];
+
+const DEFAULT_SCOPE_BOUNDARIES: [&str; 9] = [
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template",
+];
+
+/// The MathML elements which are special, and which are boundaries of the same scopes as the
+/// HTML `DEFAULT_SCOPE_BOUNDARIES`
+const MATHML_SPECIAL_ELEMENTS: [&str; 6] = ["mi", "mo", "mn", "ms", "mtext", "annotation-xml"];
+
+/// The SVG elements which are special, and which are boundaries of the same scopes as the HTML
+/// `DEFAULT_SCOPE_BOUNDARIES`
+const SVG_SPECIAL_ELEMENTS: [&str; 3] = ["foreignObject", "desc", "title"];
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(in crate::parser) enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+impl Scope {
+    fn is_boundary(self, node: &Rc<RefCell<Node>>) -> bool {
+        let node = node.borrow();
+        let tag_name = node.local_name().unwrap();
+        match node.namespace().unwrap() {
+            Namespace::Html => match self {
+                Scope::Default => DEFAULT_SCOPE_BOUNDARIES.contains(&tag_name),
+                Scope::ListItem => {
+                    DEFAULT_SCOPE_BOUNDARIES.contains(&tag_name)
+                        || tag_name == "ol"
+                        || tag_name == "ul"
+                }
+                Scope::Button => {
+                    DEFAULT_SCOPE_BOUNDARIES.contains(&tag_name) || tag_name == "button"
+                }
+                Scope::Table => matches!(tag_name, "html" | "table" | "template"),
+                Scope::Select => !matches!(tag_name, "optgroup" | "option"),
+            },
+            Namespace::MathMl => match self {
+                Scope::Table => false,
+                Scope::Select => true,
+                _ => MATHML_SPECIAL_ELEMENTS.contains(&tag_name),
+            },
+            Namespace::Svg => match self {
+                Scope::Table => false,
+                Scope::Select => true,
+                _ => SVG_SPECIAL_ELEMENTS.contains(&tag_name),
+            },
+            _ => self == Scope::Select,
+        }
+    }
+}
+
+pub(in crate::parser) fn is_special(node: &Rc<RefCell<Node>>) -> bool {
+    let node = node.borrow();
+    let special_elements: &[&str] = match node.namespace() {
+        Some(Namespace::Html) => &SPECIAL_ELEMENTS,
+        Some(Namespace::MathMl) => &MATHML_SPECIAL_ELEMENTS,
+        Some(Namespace::Svg) => &SVG_SPECIAL_ELEMENTS,
+        _ => return false,
+    };
+    special_elements.contains(&node.local_name().unwrap())
+}
+
+/// Returns `true` if the node is an HTML element with one of the given tag names
+pub(in crate::parser) fn is_element(node: &Rc<RefCell<Node>>, tag_names: &[&str]) -> bool {
+    is_element_in_namespace(node, Namespace::Html, tag_names)
+}
+
+pub(in crate::parser) fn is_element_in_namespace(
+    node: &Rc<RefCell<Node>>,
+    namespace: Namespace,
+    tag_names: &[&str],
+) -> bool {
+    let node = node.borrow();
+    node.namespace() == Some(&namespace)
+        && node
+            .local_name()
+            .is_some_and(|tag_name| tag_names.contains(&tag_name))
+}
+
+impl<T: Tokenizer, S: ErrorSink> Parser<T, S> {
+    /// Returns `true` if the current node is an HTML element with one of the given tag names
+    pub(in crate::parser) fn current_node_is(&self, tag_names: &[&str]) -> bool {
+        self.current_node()
+            .is_some_and(|node| is_element(node, tag_names))
+    }
+
+    pub(in crate::parser) fn is_open(&self, node: &Rc<RefCell<Node>>) -> bool {
+        self.open_elements
+            .iter()
+            .any(|open_element| Rc::ptr_eq(open_element, node))
+    }
+
+    /// Returns `true` if there is an HTML element with one of the given tag names anywhere in the
+    /// stack of open elements
+    pub(in crate::parser) fn has_open_element(&self, tag_names: &[&str]) -> bool {
+        self.open_elements
+            .iter()
+            .any(|node| is_element(node, tag_names))
+    }
+
+    /// Returns `true` if there is an HTML element with one of the given tag names in the stack of
+    /// open elements, without any of the scope's boundary elements below it
+    pub(in crate::parser) fn has_element_in_scope(&self, tag_names: &[&str], scope: Scope) -> bool {
+        for node in self.open_elements.iter().rev() {
+            if is_element(node, tag_names) {
+                return true;
+            }
+            if scope.is_boundary(node) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Returns `true` if the node is in the stack of open elements, without any of the default
+    /// scope's boundary elements below it
+    pub(in crate::parser) fn has_node_in_scope(&self, target: &Rc<RefCell<Node>>) -> bool {
+        for node in self.open_elements.iter().rev() {
+            if Rc::ptr_eq(node, target) {
+                return true;
+            }
+            if Scope::Default.is_boundary(node) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Pops elements until one with one of the given tag names has been popped
+    pub(in crate::parser) fn pop_until(&mut self, tag_names: &[&str]) {
+        while let Some(node) = self.open_elements.pop() {
+            if is_element(&node, tag_names) {
+                break;
+            }
+        }
+    }
+
+    /// Pops elements while the current node is one which would be closed implicitly, other than the
+    /// elements with the given tag names
+    pub(in crate::parser) fn generate_implied_end_tags_except(&mut self, excluded: &[&str]) {
+        while let Some(node) = self.current_node() {
+            if !is_element(node, &IMPLIED_END_TAG_ELEMENTS) || is_element(node, excluded) {
+                break;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    pub(in crate::parser) fn generate_implied_end_tags(&mut self) {
+        self.generate_implied_end_tags_except(&[]);
+    }
+
+    pub(in crate::parser) fn generate_all_implied_end_tags_thoroughly(&mut self) {
+        while self.current_node_is(&THOROUGHLY_IMPLIED_END_TAG_ELEMENTS) {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Pops elements until the current node is one with one of the given tag names (which is never
+    /// popped itself)
+    pub(in crate::parser) fn pop_until_current_node_is(&mut self, tag_names: &[&str]) {
+        while !self.current_node_is(tag_names) {
+            self.open_elements.pop();
+        }
+    }
+
+    pub(in crate::parser) fn clear_stack_back_to_table_context(&mut self) {
+        self.pop_until_current_node_is(&["table", "template", "html"]);
+    }
+
+    pub(in crate::parser) fn clear_stack_back_to_table_body_context(&mut self) {
+        self.pop_until_current_node_is(&["tbody", "tfoot", "thead", "template", "html"]);
+    }
+
+    pub(in crate::parser) fn clear_stack_back_to_table_row_context(&mut self) {
+        self.pop_until_current_node_is(&["tr", "template", "html"]);
+    }
+
+    /// Closes the element with the given tag name, and any elements which were implicitly closed by
+    /// it. The caller is responsible for checking that there is one in scope.
+    pub(in crate::parser) fn close_element(&mut self, tag_name: &str) {
+        self.generate_implied_end_tags_except(&[tag_name]);
+        if !self.current_node_is(&[tag_name]) {
+            self.emit_parse_error(EndTagWithUnclosedElements(String::from(tag_name)));
+        }
+        self.pop_until(&[tag_name]);
+    }
+
+    /// Closes the open `<p>` element, if there is one in button scope
+    pub(in crate::parser) fn close_p_element_in_button_scope(&mut self) {
+        if self.has_element_in_scope(&["p"], Scope::Button) {
+            self.close_element("p");
+        }
+    }
+
+    /// Returns `true` if an element not in the given list is still open (which is a parse error at
+    /// the end of the body)
+    pub(in crate::parser) fn has_unclosed_elements_at_end_of_body(&self) -> bool {
+        self.open_elements
+            .iter()
+            .any(|node| !is_element(node, &ELEMENTS_ALLOWED_OPEN_AT_END_OF_BODY))
+    }
+}
@@ -0,0 +1,137 @@
+//! Works out the document's quirks mode from its DOCTYPE, in the Initial insertion mode
+
+use crate::dom::QuirksMode;
+
+/// Public identifiers which put the document in quirks mode if they match exactly (ignoring ASCII
+/// case)
+const QUIRKS_PUBLIC_IDENTIFIERS: [&str; 3] = [
+    "-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    "-/W3C/DTD HTML 4.0 Transitional/EN",
+    "HTML",
+];
+
+/// System identifiers which put the document in quirks mode if they match exactly (ignoring ASCII
+/// case)
+const QUIRKS_SYSTEM_IDENTIFIERS: [&str; 1] =
+    ["http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd"];
+
+/// Public identifier prefixes which put the document in quirks mode (ignoring ASCII case)
+const QUIRKS_PUBLIC_IDENTIFIER_PREFIXES: [&str; 55] = [
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+/// Public identifier prefixes which put the document in quirks mode if the system identifier is
+/// missing, and otherwise in limited-quirks mode (ignoring ASCII case)
+const HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES: [&str; 2] = [
+    "-//W3C//DTD HTML 4.01 Frameset//",
+    "-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+/// Public identifier prefixes which put the document in limited-quirks mode (ignoring ASCII case)
+const LIMITED_QUIRKS_PUBLIC_IDENTIFIER_PREFIXES: [&str; 2] = [
+    "-//W3C//DTD XHTML 1.0 Frameset//",
+    "-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+pub(in crate::parser) fn quirks_mode(
+    name: Option<&str>,
+    public_identifier: Option<&str>,
+    system_identifier: Option<&str>,
+    force_quirks: bool,
+) -> QuirksMode {
+    let public_identifier_is = |ids: &[&str]| {
+        public_identifier.is_some_and(|public_identifier| {
+            ids.iter()
+                .any(|id| public_identifier.eq_ignore_ascii_case(id))
+        })
+    };
+    let public_identifier_starts_with = |prefixes: &[&str]| {
+        public_identifier.is_some_and(|public_identifier| {
+            prefixes
+                .iter()
+                .any(|prefix| starts_with_ignore_ascii_case(public_identifier, prefix))
+        })
+    };
+    let system_identifier_is = |ids: &[&str]| {
+        system_identifier.is_some_and(|system_identifier| {
+            ids.iter()
+                .any(|id| system_identifier.eq_ignore_ascii_case(id))
+        })
+    };
+
+    if force_quirks
+        || name != Some("html")
+        || public_identifier_is(&QUIRKS_PUBLIC_IDENTIFIERS)
+        || system_identifier_is(&QUIRKS_SYSTEM_IDENTIFIERS)
+        || public_identifier_starts_with(&QUIRKS_PUBLIC_IDENTIFIER_PREFIXES)
+        || (system_identifier.is_none()
+            && public_identifier_starts_with(&HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES))
+    {
+        QuirksMode::Quirks
+    } else if public_identifier_starts_with(&LIMITED_QUIRKS_PUBLIC_IDENTIFIER_PREFIXES)
+        || (system_identifier.is_some()
+            && public_identifier_starts_with(&HTML_4_01_PUBLIC_IDENTIFIER_PREFIXES))
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+fn starts_with_ignore_ascii_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
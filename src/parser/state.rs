@@ -1,5 +1,4 @@
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-#[allow(dead_code)]
 pub(crate) enum InsertionMode {
     Initial,
     BeforeHtml,
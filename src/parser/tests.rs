@@ -1,35 +1,1048 @@
-use crate::dom::Node;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::dom::{Attribute, Namespace, Node, QuirksMode};
+use crate::error::Error;
+use crate::parser::error::{InvalidContextError, TreeConstructionError};
 use crate::parser::Parser;
+use crate::sink::Ignore;
+use crate::tokenizer::error::ParseError;
 use crate::tokenizer::result::Result;
+use crate::tokenizer::span::{Span, Spanned};
 use crate::tokenizer::Token::*;
-use crate::tokenizer::{Token, Tokenizer};
+use crate::tokenizer::{Attributes, TagKind, Token, Tokenizer, TokenizerImpl};
 
-// TODO: #[test]
+#[test]
 fn empty_document() {
     let mock_tokenizer = MockTokenizer::new(vec![Ok(EndOfFile)]);
-    let mut parser = Parser::new(mock_tokenizer);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    expected.borrow_mut().set_quirks_mode(QuirksMode::Quirks);
+    let html = element("html", &expected, &expected);
+    element("head", &expected, &html);
+    element("body", &expected, &html);
+    assert_eq!(&actual, &expected);
+    assert_eq!(errors, [TreeConstructionError::MissingDoctype]);
+}
+
+#[test]
+fn stops_when_the_tokenizer_error_sink_stops_tokenization() {
+    let mock_tokenizer = MockTokenizer::new(vec![Err(ParseError::EofInTag), Ok(EndOfFile)]);
+    let mut parser = Parser::new(mock_tokenizer, Ignore);
+
+    let actual = parser.parse().map_err(|error| error.value);
+
+    assert_eq!(actual, Err(Error::Tokenizer(ParseError::EofInTag)));
+}
+
+#[test]
+fn shared_error_sink_sees_every_parse_error_in_order() {
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::with_error_sink("<p =a></i>".into(), &mut errors);
+
+    parser.parse().unwrap();
+
+    drop(parser);
+    assert_eq!(
+        errors,
+        [
+            Error::from(ParseError::UnexpectedEqualsSignBeforeAttributeName),
+            Error::from(TreeConstructionError::MissingDoctype),
+            Error::from(TreeConstructionError::UnexpectedEndTag("i".into())),
+        ]
+    );
+}
+
+#[test]
+fn strict_mode_returns_the_first_parse_error() {
+    let input = "\n<=";
+    let mut parser = Parser::strict(input.into());
+
+    let actual = parser.parse().unwrap_err();
+
+    assert_eq!(actual.value, ParseError::InvalidFirstCharacterOfTagName);
+    assert_eq!(actual.span.start.to_string(), "2:2");
+}
+
+#[test]
+fn html5_doctype() {
+    let mock_tokenizer = MockTokenizer::new(vec![Ok(doctype("html", None, None))]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    assert_eq!(&actual, &expected);
+    assert_eq!(actual.borrow().quirks_mode(), Some(QuirksMode::NoQuirks));
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn comment_before_doctype() {
+    let mock_tokenizer = MockTokenizer::new(vec![
+        Ok(Comment { data: "a".into() }),
+        Ok(Character('\n')),
+        Ok(doctype("html", None, Some("about:legacy-compat"))),
+    ]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    Node::create_comment("a".into(), &expected, &expected);
+    Node::create_doctype(
+        "html".into(),
+        "".into(),
+        "about:legacy-compat".into(),
+        &expected,
+    );
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn legacy_doctypes_set_quirks_mode() {
+    let html_4_01 = "-//W3C//DTD HTML 4.01 Transitional//EN";
+    let html_4_01_dtd = "http://www.w3.org/TR/html4/loose.dtd";
+    let cases = [
+        (doctype("html", Some(html_4_01), None), QuirksMode::Quirks),
+        (
+            doctype("html", Some(html_4_01), Some(html_4_01_dtd)),
+            QuirksMode::LimitedQuirks,
+        ),
+        (
+            doctype("html", Some("-//w3c//dtd html 3.2 final//en"), None),
+            QuirksMode::Quirks,
+        ),
+        (doctype("html", Some("HTML"), None), QuirksMode::Quirks),
+        (doctype("svg", None, None), QuirksMode::Quirks),
+        (
+            doctype("html", None, Some("http://www.w3.org/TR/html4/strict.dtd")),
+            QuirksMode::NoQuirks,
+        ),
+    ];
+    for (token, quirks_mode) in cases.iter().cloned() {
+        let mock_tokenizer = MockTokenizer::new(vec![Ok(token)]);
+        let mut errors: Vec<Error> = Vec::new();
+        let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+        let actual = parser.parse().unwrap();
+
+        assert_eq!(actual.borrow().quirks_mode(), Some(quirks_mode));
+        assert_eq!(errors, [TreeConstructionError::NonConformingDoctype]);
+    }
+}
+
+#[test]
+fn iframe_srcdoc_is_never_in_quirks_mode() {
+    let html_3_2 = "-//W3C//DTD HTML 3.2//EN";
+    let mock_tokenizer = MockTokenizer::new(vec![Ok(doctype("html", Some(html_3_2), None))]);
+    let mut parser = Parser::new(mock_tokenizer, Ignore);
+    parser.set_iframe_srcdoc(true);
+
+    let actual = parser.parse().unwrap();
+
+    assert_eq!(actual.borrow().quirks_mode(), Some(QuirksMode::NoQuirks));
+}
+
+#[test]
+fn head_with_title() {
+    let mock_tokenizer = MockTokenizer::new(vec![
+        Ok(doctype("html", None, None)),
+        Ok(start_tag("html")),
+        Ok(start_tag("head")),
+        Ok(start_tag("title")),
+        Ok(Character('a')),
+        Ok(Character('b')),
+        Ok(end_tag("title")),
+        Ok(end_tag("head")),
+    ]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    let title = element("title", &expected, &head);
+    Node::create_text("ab", &expected, &title);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn implied_html_and_head_elements() {
+    let mock_tokenizer = MockTokenizer::new(vec![
+        Ok(doctype("html", None, None)),
+        Ok(Character('\n')),
+        Ok(Comment { data: "a".into() }),
+        Ok(Character(' ')),
+        Ok(start_tag("meta")),
+        Ok(Character(' ')),
+        Ok(start_tag("noscript")),
+        Ok(start_tag("link")),
+        Ok(end_tag("noscript")),
+    ]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    Node::create_comment("a".into(), &expected, &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    element("meta", &expected, &head);
+    Node::create_text(" ", &expected, &head);
+    let noscript = element("noscript", &expected, &head);
+    element("link", &expected, &noscript);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn head_element_after_head() {
+    let mock_tokenizer = MockTokenizer::new(vec![
+        Ok(doctype("html", None, None)),
+        Ok(start_tag("head")),
+        Ok(end_tag("head")),
+        Ok(Character(' ')),
+        Ok(start_tag("style")),
+        Ok(Character('a')),
+        Ok(end_tag("style")),
+        Ok(end_tag("p")),
+    ]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
 
-    let actual = parser.parse();
+    let actual = parser.parse().unwrap();
 
     let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    let style = element("style", &expected, &head);
+    Node::create_text("a", &expected, &style);
+    Node::create_text(" ", &expected, &html);
     assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            TreeConstructionError::UnexpectedStartTag("style".into()),
+            TreeConstructionError::UnexpectedEndTag("p".into()),
+        ]
+    );
+}
+
+#[test]
+fn start_tag_in_rcdata_from_a_tokenizer_which_ignores_state_changes() {
+    let mock_tokenizer = MockTokenizer::new(vec![
+        Ok(doctype("html", None, None)),
+        Ok(start_tag("title")),
+        Ok(Character('a')),
+        Ok(start_tag("meta")),
+        Ok(end_tag("head")),
+    ]);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(mock_tokenizer, &mut errors);
+
+    let actual = parser.parse().unwrap();
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    let title = element("title", &expected, &head);
+    Node::create_text("a", &expected, &title);
+    element("meta", &expected, &head);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::UnexpectedStartTag("meta".into())]
+    );
+}
+
+#[test]
+fn misnested_formatting_elements() {
+    let (actual, errors) = parse("<!DOCTYPE html><b><i></b>x</i>");
+
+    let (expected, body) = html5_document();
+    let b = element("b", &expected, &body);
+    element("i", &expected, &b);
+    let i = element("i", &expected, &body);
+    Node::create_text("x", &expected, &i);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::MisnestedFormattingElement(
+            "b".into()
+        )]
+    );
+}
+
+#[test]
+fn formatting_elements_misnested_with_each_other() {
+    let (actual, errors) = parse("<!DOCTYPE html><p>1<b>2<i>3</b>4</i>5</p>");
+
+    let (expected, body) = html5_document();
+    let p = element("p", &expected, &body);
+    Node::create_text("1", &expected, &p);
+    let b = element("b", &expected, &p);
+    Node::create_text("2", &expected, &b);
+    let i = element("i", &expected, &b);
+    Node::create_text("3", &expected, &i);
+    let i = element("i", &expected, &p);
+    Node::create_text("4", &expected, &i);
+    Node::create_text("5", &expected, &p);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::MisnestedFormattingElement(
+            "b".into()
+        )]
+    );
+}
+
+#[test]
+fn formatting_element_misnested_with_a_block() {
+    let (actual, errors) = parse("<!DOCTYPE html><b>1<p>2</b>3</p>");
+
+    let (expected, body) = html5_document();
+    let b = element("b", &expected, &body);
+    Node::create_text("1", &expected, &b);
+    let p = element("p", &expected, &body);
+    let b = element("b", &expected, &p);
+    Node::create_text("2", &expected, &b);
+    Node::create_text("3", &expected, &p);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::MisnestedFormattingElement(
+            "b".into()
+        )]
+    );
+}
+
+#[test]
+fn at_most_three_identical_formatting_elements_are_reconstructed() {
+    let (actual, errors) = parse("<!DOCTYPE html><p><b><b><b><b><p>x");
+
+    let (expected, body) = html5_document();
+    let p = element("p", &expected, &body);
+    let mut parent = p;
+    for _ in 0..4 {
+        parent = element("b", &expected, &parent);
+    }
+    let p = element("p", &expected, &body);
+    let mut parent = p;
+    for _ in 0..3 {
+        parent = element("b", &expected, &parent);
+    }
+    Node::create_text("x", &expected, &parent);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            TreeConstructionError::EndTagWithUnclosedElements("p".into()),
+            TreeConstructionError::EofWithUnclosedElements,
+        ]
+    );
+}
+
+#[test]
+fn nested_a_element_closes_the_open_one() {
+    let (actual, errors) = parse("<!DOCTYPE html><a>1<a>2");
+
+    let (expected, body) = html5_document();
+    let a = element("a", &expected, &body);
+    Node::create_text("1", &expected, &a);
+    let a = element("a", &expected, &body);
+    Node::create_text("2", &expected, &a);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            TreeConstructionError::UnexpectedStartTag("a".into()),
+            TreeConstructionError::EofWithUnclosedElements,
+        ]
+    );
+}
+
+#[test]
+fn implied_end_tags_in_body() {
+    let (actual, errors) = parse("<!DOCTYPE html><pre>\n\nx</pre><ul><li>a<li>b</ul>");
+
+    let (expected, body) = html5_document();
+    let pre = element("pre", &expected, &body);
+    Node::create_text("\nx", &expected, &pre);
+    let ul = element("ul", &expected, &body);
+    let li = element("li", &expected, &ul);
+    Node::create_text("a", &expected, &li);
+    let li = element("li", &expected, &ul);
+    Node::create_text("b", &expected, &li);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn only_void_elements_acknowledge_the_self_closing_flag() {
+    let (actual, errors) = parse("<!DOCTYPE html><br/><div/></div>");
+
+    let (expected, body) = html5_document();
+    element("br", &expected, &body);
+    element("div", &expected, &body);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [ParseError::NonVoidHtmlElementStartTagWithTrailingSolidus]
+    );
+}
+
+#[test]
+fn table() {
+    let (actual, errors) = parse("<!DOCTYPE html><table> <tr> </tr> </table>");
+
+    let (expected, body) = html5_document();
+    let table = element("table", &expected, &body);
+    Node::create_text(" ", &expected, &table);
+    let tbody = element("tbody", &expected, &table);
+    let tr = element("tr", &expected, &tbody);
+    Node::create_text(" ", &expected, &tr);
+    Node::create_text(" ", &expected, &tbody);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn implied_table_elements() {
+    let (actual, errors) = parse("<!DOCTYPE html><table><caption>a<col><td>b</table>");
+
+    let (expected, body) = html5_document();
+    let table = element("table", &expected, &body);
+    let caption = element("caption", &expected, &table);
+    Node::create_text("a", &expected, &caption);
+    let colgroup = element("colgroup", &expected, &table);
+    element("col", &expected, &colgroup);
+    let tbody = element("tbody", &expected, &table);
+    let tr = element("tr", &expected, &tbody);
+    let td = element("td", &expected, &tr);
+    Node::create_text("b", &expected, &td);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::UnexpectedStartTag("td".into())]
+    );
+}
+
+#[test]
+fn text_in_table_is_foster_parented() {
+    let (actual, errors) = parse("<!DOCTYPE html><table>a<tr><td>b</table>");
+
+    let (expected, body) = html5_document();
+    Node::create_text("a", &expected, &body);
+    let table = element("table", &expected, &body);
+    let tbody = element("tbody", &expected, &table);
+    let tr = element("tr", &expected, &tbody);
+    let td = element("td", &expected, &tr);
+    Node::create_text("b", &expected, &td);
+    assert_eq!(&actual, &expected);
+    assert_eq!(errors, [TreeConstructionError::FosterParentedContent]);
+}
+
+#[test]
+fn elements_in_table_are_foster_parented() {
+    let (actual, errors) = parse("<!DOCTYPE html><p>a<table><b>b</b><tr><td>c</table>");
+
+    let (expected, body) = html5_document();
+    let p = element("p", &expected, &body);
+    Node::create_text("a", &expected, &p);
+    let b = element("b", &expected, &body);
+    Node::create_text("b", &expected, &b);
+    let table = element("table", &expected, &body);
+    let tbody = element("tbody", &expected, &table);
+    let tr = element("tr", &expected, &tbody);
+    let td = element("td", &expected, &tr);
+    Node::create_text("c", &expected, &td);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            TreeConstructionError::FosterParentedContent,
+            TreeConstructionError::FosterParentedContent,
+            TreeConstructionError::FosterParentedContent,
+        ]
+    );
+}
+
+#[test]
+fn template() {
+    let (actual, errors) = parse("<!DOCTYPE html><template><tr><td>a</template>b");
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    let template = element("template", &expected, &head);
+    let contents = template.borrow().template_contents().unwrap().clone();
+    let tr = element("tr", &expected, &contents);
+    let td = element("td", &expected, &tr);
+    Node::create_text("a", &expected, &td);
+    let body = element("body", &expected, &html);
+    Node::create_text("b", &expected, &body);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn foster_parenting_in_template() {
+    let (actual, errors) = parse("<!DOCTYPE html><template><table>a</table></template>");
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    let head = element("head", &expected, &html);
+    let template = element("template", &expected, &head);
+    let contents = template.borrow().template_contents().unwrap().clone();
+    Node::create_text("a", &expected, &contents);
+    element("table", &expected, &contents);
+    element("body", &expected, &html);
+    assert_eq!(&actual, &expected);
+    assert_eq!(errors, [TreeConstructionError::FosterParentedContent]);
+}
+
+#[test]
+fn select() {
+    let (actual, errors) =
+        parse("<!DOCTYPE html><select><option>a<option>b<optgroup><option>c</select><input>");
+
+    let (expected, body) = html5_document();
+    let select = element("select", &expected, &body);
+    let option = element("option", &expected, &select);
+    Node::create_text("a", &expected, &option);
+    let option = element("option", &expected, &select);
+    Node::create_text("b", &expected, &option);
+    let optgroup = element("optgroup", &expected, &select);
+    let option = element("option", &expected, &optgroup);
+    Node::create_text("c", &expected, &option);
+    element("input", &expected, &body);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn select_in_table() {
+    let (actual, errors) = parse("<!DOCTYPE html><table><td><select><option>a<td>b</table>");
+
+    let (expected, body) = html5_document();
+    let table = element("table", &expected, &body);
+    let tbody = element("tbody", &expected, &table);
+    let tr = element("tr", &expected, &tbody);
+    let td = element("td", &expected, &tr);
+    let select = element("select", &expected, &td);
+    let option = element("option", &expected, &select);
+    Node::create_text("a", &expected, &option);
+    let td = element("td", &expected, &tr);
+    Node::create_text("b", &expected, &td);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            TreeConstructionError::UnexpectedStartTag("td".into()),
+            TreeConstructionError::UnexpectedStartTag("td".into()),
+        ]
+    );
+}
+
+#[test]
+fn frameset() {
+    let (actual, errors) = parse(
+        "<!DOCTYPE html><frameset><frame><frameset></frameset></frameset><noframes>a</noframes>",
+    );
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    element("head", &expected, &html);
+    let frameset = element("frameset", &expected, &html);
+    element("frame", &expected, &frameset);
+    element("frameset", &expected, &frameset);
+    let noframes = element("noframes", &expected, &html);
+    Node::create_text("a", &expected, &noframes);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn frameset_replaces_the_body() {
+    let (actual, errors) = parse("<!DOCTYPE html><div><frameset><frame></frameset>");
+
+    let expected = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &expected);
+    let html = element("html", &expected, &expected);
+    element("head", &expected, &html);
+    let frameset = element("frameset", &expected, &html);
+    element("frame", &expected, &frameset);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::UnexpectedStartTag("frameset".into())]
+    );
+}
+
+#[test]
+fn content_after_body() {
+    let (actual, errors) = parse("<!DOCTYPE html><p>a</body> <!--b--></html><!--c-->d");
+
+    let (expected, body) = html5_document();
+    let p = element("p", &expected, &body);
+    Node::create_text("a d", &expected, &p);
+    let html = body.borrow().parent.as_ref().unwrap().upgrade().unwrap();
+    Node::create_comment("b".into(), &expected, &html);
+    Node::create_comment("c".into(), &expected, &expected);
+    assert_eq!(&actual, &expected);
+    assert_eq!(errors, [TreeConstructionError::UnexpectedCharacter]);
+}
+
+#[test]
+fn svg() {
+    let (actual, errors) = parse(concat!(
+        "<!DOCTYPE html><svg viewbox='0 0 1 1'><lineargradient/><a xlink:href='#a'><path/></a>",
+        "<foreignobject><p>a</p></foreignobject><![CDATA[b<c]]></svg>d",
+    ));
+
+    let (expected, body) = html5_document();
+    let attributes = vec![Attribute::new("viewBox".into(), "0 0 1 1".into())];
+    let svg = foreign_element(Namespace::Svg, "svg", attributes, &expected, &body);
+    let attributes = Vec::new();
+    foreign_element(
+        Namespace::Svg,
+        "linearGradient",
+        attributes,
+        &expected,
+        &svg,
+    );
+    let attributes = vec![Attribute::new_ns(
+        Namespace::XLink,
+        Some("xlink".into()),
+        "href".into(),
+        "#a".into(),
+    )];
+    let a = foreign_element(Namespace::Svg, "a", attributes, &expected, &svg);
+    foreign_element(Namespace::Svg, "path", Vec::new(), &expected, &a);
+    let attributes = Vec::new();
+    let foreign_object =
+        foreign_element(Namespace::Svg, "foreignObject", attributes, &expected, &svg);
+    let p = element("p", &expected, &foreign_object);
+    Node::create_text("a", &expected, &p);
+    Node::create_text("b<c", &expected, &svg);
+    Node::create_text("d", &expected, &body);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn namespaced_attributes() {
+    let (document, _) = parse("<svg xlink:href=a xml:lang=en xmlns=b xmlns:xlink=c>");
+
+    let html = document.borrow().children[0].clone();
+    let body = html.borrow().children[1].clone();
+    let svg = body.borrow().children[0].clone();
+    let svg = svg.borrow();
+    assert_eq!(svg.namespace(), Some(&Namespace::Svg));
+    assert_eq!(svg.prefix(), None);
+    assert_eq!(svg.local_name(), Some("svg"));
+    let attributes: Vec<_> = svg
+        .attributes()
+        .unwrap()
+        .iter()
+        .map(|attr| {
+            (
+                attr.namespace(),
+                attr.prefix(),
+                attr.local_name(),
+                attr.value(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            (Some(&Namespace::XLink), Some("xlink"), "href", "a"),
+            (Some(&Namespace::Xml), Some("xml"), "lang", "en"),
+            (Some(&Namespace::XmlNs), None, "xmlns", "b"),
+            (Some(&Namespace::XmlNs), Some("xmlns"), "xlink", "c"),
+        ]
+    );
+    assert_eq!(svg.attribute("xlink:href"), Some("a"));
+    assert_eq!(svg.attribute("href"), None);
+}
+
+#[test]
+fn html_start_tag_breaks_out_of_svg() {
+    let (actual, errors) = parse("<!DOCTYPE html><svg><g><p>a");
+
+    let (expected, body) = html5_document();
+    let svg = foreign_element(Namespace::Svg, "svg", Vec::new(), &expected, &body);
+    foreign_element(Namespace::Svg, "g", Vec::new(), &expected, &svg);
+    let p = element("p", &expected, &body);
+    Node::create_text("a", &expected, &p);
+    assert_eq!(&actual, &expected);
+    assert_eq!(
+        errors,
+        [TreeConstructionError::UnexpectedStartTag("p".into())]
+    );
+}
+
+#[test]
+fn mathml_integration_points() {
+    let (actual, errors) = parse(concat!(
+        "<!DOCTYPE html><math definitionurl='x'><mi>a<b>b</b></mi>",
+        "<annotation-xml encoding='text/html'><div>c</div></annotation-xml></math>",
+    ));
+
+    let (expected, body) = html5_document();
+    let attributes = vec![Attribute::new("definitionURL".into(), "x".into())];
+    let math = foreign_element(Namespace::MathMl, "math", attributes, &expected, &body);
+    let mi = foreign_element(Namespace::MathMl, "mi", Vec::new(), &expected, &math);
+    Node::create_text("a", &expected, &mi);
+    let b = element("b", &expected, &mi);
+    Node::create_text("b", &expected, &b);
+    let attributes = vec![Attribute::new("encoding".into(), "text/html".into())];
+    let annotation_xml = foreign_element(
+        Namespace::MathMl,
+        "annotation-xml",
+        attributes,
+        &expected,
+        &math,
+    );
+    let div = element("div", &expected, &annotation_xml);
+    Node::create_text("c", &expected, &div);
+    assert_eq!(&actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn fragment() {
+    let document = Node::create_document();
+    let div = Node::create_detached_element(Namespace::Html, "div".into(), Vec::new(), &document);
+
+    let (actual, errors) = parse_fragment(&div, "<p>a</p>b</html>");
+
+    let (expected_document, root) = fragment_root();
+    let p = element("p", &expected_document, &root);
+    Node::create_text("a", &expected_document, &p);
+    Node::create_text("b", &expected_document, &root);
+    assert_eq!(actual, root.borrow().children);
+    assert!(actual.iter().all(|node| node.borrow().parent.is_none()));
+    assert_eq!(
+        errors,
+        [TreeConstructionError::UnexpectedEndTag("html".into())]
+    );
+    assert!(div.borrow().children.is_empty());
+}
+
+#[test]
+fn fragment_in_table_row() {
+    let document = Node::create_document();
+    let tr = Node::create_detached_element(Namespace::Html, "tr".into(), Vec::new(), &document);
+
+    let (actual, errors) = parse_fragment(&tr, "<td>a<td>b");
+
+    let (expected_document, root) = fragment_root();
+    let td = element("td", &expected_document, &root);
+    Node::create_text("a", &expected_document, &td);
+    let td = element("td", &expected_document, &root);
+    Node::create_text("b", &expected_document, &td);
+    assert_eq!(actual, root.borrow().children);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn fragment_in_raw_text_element() {
+    let document = Node::create_document();
+    let textarea =
+        Node::create_detached_element(Namespace::Html, "textarea".into(), Vec::new(), &document);
+
+    let (actual, errors) = parse_fragment(&textarea, "<b>a&amp;b</textarea>");
+
+    let (expected_document, root) = fragment_root();
+    Node::create_text("<b>a&b</textarea>", &expected_document, &root);
+    assert_eq!(actual, root.borrow().children);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn fragment_in_svg() {
+    let document = Node::create_document();
+    let svg = Node::create_detached_element(Namespace::Svg, "svg".into(), Vec::new(), &document);
+
+    let (actual, errors) = parse_fragment(&svg, "<lineargradient/><![CDATA[a]]>");
+
+    let (expected_document, root) = fragment_root();
+    let attributes = Vec::new();
+    foreign_element(
+        Namespace::Svg,
+        "linearGradient",
+        attributes,
+        &expected_document,
+        &root,
+    );
+    Node::create_text("a", &expected_document, &root);
+    assert_eq!(actual, root.borrow().children);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn fragment_in_template() {
+    let document = Node::create_document();
+    let template =
+        Node::create_detached_element(Namespace::Html, "template".into(), Vec::new(), &document);
+
+    let actual = Parser::parse_fragment(&template, "<td>a<td>b".into()).unwrap();
+
+    let (expected_document, root) = fragment_root();
+    let td = element("td", &expected_document, &root);
+    Node::create_text("a", &expected_document, &td);
+    let td = element("td", &expected_document, &root);
+    Node::create_text("b", &expected_document, &td);
+    assert_eq!(actual, root.borrow().children);
+}
+
+#[test]
+fn fragment_in_non_element() {
+    let document = Node::create_document();
+    let comment = Node::create_detached_comment("a".into(), &document);
+
+    let actual = Parser::parse_fragment(&comment, "<p>b".into());
+
+    assert_eq!(actual, Err(InvalidContextError));
+}
+
+#[test]
+#[should_panic]
+fn fragment_in_non_element_with_error_sink() {
+    let document = Node::create_document();
+    let comment = Node::create_detached_comment("a".into(), &document);
+
+    let _ = parse_fragment(&comment, "<p>b");
+}
+
+#[test]
+fn never_panics() {
+    // fragments which between them reach every insertion mode
+    const FRAGMENTS: [&str; 68] = [
+        "<!DOCTYPE html>",
+        "<html>",
+        "</html>",
+        "<head>",
+        "</head>",
+        "<body>",
+        "</body>",
+        "<p>",
+        "</p>",
+        "<b>",
+        "</b>",
+        "<i>",
+        "</i>",
+        "<a>",
+        "</a>",
+        "<nobr>",
+        "<div>",
+        "</div>",
+        "<table>",
+        "</table>",
+        "<caption>",
+        "</caption>",
+        "<colgroup>",
+        "<col>",
+        "<tbody>",
+        "</tbody>",
+        "<tr>",
+        "</tr>",
+        "<td>",
+        "</td>",
+        "<select>",
+        "</select>",
+        "<option>",
+        "<optgroup>",
+        "<template>",
+        "</template>",
+        "<frameset>",
+        "</frameset>",
+        "<frame>",
+        "<noframes>",
+        "<title>",
+        "<textarea>",
+        "<li>",
+        "<object>",
+        "<!--x-->",
+        "x",
+        " ",
+        "\0",
+        "<form>",
+        "</form>",
+        "<input type=hidden>",
+        "<button>",
+        "<h1>",
+        "</h2>",
+        "<pre>\n",
+        "<plaintext>",
+        "<ruby>",
+        "<rt>",
+        "<script>",
+        "</script>",
+        "<marquee>",
+        "</br>",
+        "<hr/>",
+        "<image>",
+        "<svg>",
+        "<math>",
+        "<noscript>",
+        "<style>",
+    ];
+
+    // a xorshift generator, so the inputs are the same every run
+    let mut state: u32 = 0x2545_f491;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as usize
+    };
+    for _ in 0..2000 {
+        let len = next() % 32;
+        let input: String = (0..len)
+            .map(|_| FRAGMENTS[next() % FRAGMENTS.len()])
+            .collect();
+
+        let mut parser = Parser::new(TokenizerImpl::new(input.clone(), Ignore), Ignore);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parser.parse()));
+
+        assert!(result.is_ok(), "panicked parsing {:?}", input);
+    }
+}
+
+/// Parses the input with the real tokenizer, collecting the tree builder's parse errors
+fn parse(input: &str) -> (Rc<RefCell<Node>>, Vec<Error>) {
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(TokenizerImpl::new(input.into(), Ignore), &mut errors);
+    let document = parser.parse().unwrap();
+    (document, errors)
+}
+
+fn parse_fragment(
+    context_element: &Rc<RefCell<Node>>,
+    input: &str,
+) -> (Vec<Rc<RefCell<Node>>>, Vec<Error>) {
+    let mut errors: Vec<Error> = Vec::new();
+    let mut parser = Parser::new(TokenizerImpl::new(input.into(), Ignore), &mut errors);
+    let nodes = parser.parse_in_context(context_element).unwrap();
+    (nodes, errors)
+}
+
+/// Returns a document with the `<html>` element a fragment is parsed into, whose children are the
+/// parsed nodes
+fn fragment_root() -> (Rc<RefCell<Node>>, Rc<RefCell<Node>>) {
+    let document = Node::create_document();
+    let root = element("html", &document, &document);
+    (document, root)
+}
+
+/// Returns a document with an HTML5 DOCTYPE, along with its (empty) body
+fn html5_document() -> (Rc<RefCell<Node>>, Rc<RefCell<Node>>) {
+    let document = Node::create_document();
+    Node::create_doctype("html".into(), "".into(), "".into(), &document);
+    let html = element("html", &document, &document);
+    element("head", &document, &html);
+    let body = element("body", &document, &html);
+    (document, body)
+}
+
+fn element(
+    tag_name: &str,
+    document: &Rc<RefCell<Node>>,
+    parent: &Rc<RefCell<Node>>,
+) -> Rc<RefCell<Node>> {
+    Node::create_element(tag_name.into(), Vec::new(), document, parent)
+}
+
+fn foreign_element(
+    namespace: Namespace,
+    tag_name: &str,
+    attributes: Vec<Attribute>,
+    document: &Rc<RefCell<Node>>,
+    parent: &Rc<RefCell<Node>>,
+) -> Rc<RefCell<Node>> {
+    Node::create_element_ns(
+        namespace,
+        None,
+        tag_name.into(),
+        attributes,
+        document,
+        parent,
+    )
+}
+
+fn start_tag(tag_name: &str) -> Token {
+    Tag {
+        kind: TagKind::Start,
+        tag_name: String::from(tag_name),
+        self_closing: false,
+        attributes: Attributes::new(),
+    }
+}
+
+fn end_tag(tag_name: &str) -> Token {
+    Tag {
+        kind: TagKind::End,
+        tag_name: String::from(tag_name),
+        self_closing: false,
+        attributes: Attributes::new(),
+    }
+}
+
+fn doctype(name: &str, public_identifier: Option<&str>, system_identifier: Option<&str>) -> Token {
+    Doctype {
+        name: Some(name.into()),
+        public_identifier: public_identifier.map(String::from),
+        system_identifier: system_identifier.map(String::from),
+        force_quirks: false,
+    }
 }
 
 struct MockTokenizer {
-    tokens: Vec<Result<Token>>,
+    tokens: Vec<std::result::Result<Token, ParseError>>,
 }
 
 impl MockTokenizer {
-    pub fn new(tokens: Vec<Result<Token>>) -> Self {
+    pub fn new(mut tokens: Vec<std::result::Result<Token, ParseError>>) -> Self {
+        // so they can be popped off the end in order
+        tokens.reverse();
         Self { tokens }
     }
 }
 
 impl Iterator for MockTokenizer {
-    type Item = Result<Token>;
+    type Item = Result<Spanned<Token>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.tokens.pop()
+        // the spans don't matter to the tree builder
+        let span = Span::default();
+        self.tokens.pop().map(|output| {
+            output
+                .map(|token| Spanned::new(token, span))
+                .map_err(|error| Spanned::new(error, span))
+        })
     }
 }
 
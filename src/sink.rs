@@ -0,0 +1,100 @@
+//! Where the tokenizer and the tree builder send the parse errors they find. Most parse errors
+//! can be recovered from, so by default parsing carries on regardless; a sink can stop it early.
+
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::tokenizer::span::Spanned;
+
+pub trait ErrorSink {
+    /// Called with each parse error as it is found. Returning `ControlFlow::Break` stops parsing
+    /// (the error is then also returned to the caller in place of the next token).
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()>;
+}
+
+/// Collects every parse error along with its position
+impl ErrorSink for Vec<Spanned<Error>> {
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()> {
+        self.push(error);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Collects every parse error, dropping the positions
+impl ErrorSink for Vec<Error> {
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()> {
+        self.push(error.value);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Lets the caller keep hold of the sink (eg. to look at the collected errors afterwards)
+impl<S: ErrorSink + ?Sized> ErrorSink for &mut S {
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()> {
+        (**self).report(error)
+    }
+}
+
+/// Carries on parsing regardless of any parse errors
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Ignore;
+
+impl ErrorSink for Ignore {
+    fn report(&mut self, _: Spanned<Error>) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Stops parsing at the first parse error, and keeps hold of it
+#[derive(Clone, Default, Debug)]
+pub struct FailFast {
+    first_error: Option<Spanned<Error>>,
+}
+
+impl FailFast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn first_error(&self) -> Option<&Spanned<Error>> {
+        self.first_error.as_ref()
+    }
+}
+
+impl ErrorSink for FailFast {
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()> {
+        self.first_error.get_or_insert(error);
+        ControlFlow::Break(())
+    }
+}
+
+/// Lets the tokenizer and the tree builder report to the same sink, so it sees every parse error in
+/// the order they were found
+#[derive(Debug)]
+pub struct Shared<S: ErrorSink> {
+    sink: Rc<RefCell<S>>,
+}
+
+impl<S: ErrorSink> Shared<S> {
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink: Rc::new(RefCell::new(sink)),
+        }
+    }
+}
+
+impl<S: ErrorSink> Clone for Shared<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+impl<S: ErrorSink> ErrorSink for Shared<S> {
+    fn report(&mut self, error: Spanned<Error>) -> ControlFlow<()> {
+        self.sink.borrow_mut().report(error)
+    }
+}
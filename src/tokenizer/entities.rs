@@ -0,0 +1,55 @@
+//! The named character references table, compiled by `build.rs` into a trie over the reference
+//! names (without the leading `&`)
+
+include!(concat!(env!("OUT_DIR"), "/named_character_references.rs"));
+
+/// Finds the longest named character reference which is a prefix of the input, one character at a
+/// time
+pub(in crate::tokenizer) struct NamedCharacterReferenceMatcher {
+    node: Option<usize>,
+    consumed: usize,
+    longest_match: Option<(usize, &'static str)>,
+}
+
+impl NamedCharacterReferenceMatcher {
+    pub(in crate::tokenizer) fn new() -> Self {
+        Self {
+            node: Some(0),
+            consumed: 0,
+            longest_match: None,
+        }
+    }
+
+    /// Returns `true` if there may be a longer match after this character
+    pub(in crate::tokenizer) fn push(&mut self, ch: char) -> bool {
+        self.node = self.node.and_then(|node| Self::child(node, ch));
+        if let Some(node) = self.node {
+            self.consumed += 1;
+            let (first_edge, last_edge, characters_idx) = NODES[node];
+            if characters_idx != 0 {
+                let characters = CHARACTERS[usize::from(characters_idx) - 1];
+                self.longest_match = Some((self.consumed, characters));
+            }
+            first_edge != last_edge
+        } else {
+            false
+        }
+    }
+
+    /// Returns the length of the name (always ASCII) and the characters it refers to
+    pub(in crate::tokenizer) fn longest_match(&self) -> Option<(usize, &'static str)> {
+        self.longest_match
+    }
+
+    fn child(node: usize, ch: char) -> Option<usize> {
+        if !ch.is_ascii() {
+            return None;
+        }
+        let (first_edge, last_edge, _) = NODES[node];
+        let edges = &EDGES[usize::from(first_edge)..usize::from(last_edge)];
+        edges
+            .binary_search_by_key(&(ch as u8), |&(byte, _)| byte)
+            .ok()
+            .map(|idx| usize::from(edges[idx].1))
+    }
+}
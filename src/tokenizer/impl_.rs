@@ -1,11 +1,18 @@
 use std::char::REPLACEMENT_CHARACTER;
+use std::convert::TryInto;
 
+use super::entities::NamedCharacterReferenceMatcher;
 use super::error::ParseError::*;
 use super::state::State::*;
 use super::util::Codepoint::*;
+use super::util::{
+    is_ascii_whitespace, is_control, is_noncharacter, is_surrogate,
+    numeric_character_reference_replacement,
+};
 use super::TokenizerImpl;
+use crate::sink::ErrorSink;
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_data(&mut self) {
         let codepoint = self.next_input_character();
         match codepoint {
@@ -31,23 +38,85 @@ impl TokenizerImpl {
     }
 
     pub(in crate::tokenizer) fn handle_rcdata(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('&') => {
+                debug_assert_eq!(self.return_state, None);
+                self.return_state = Some(RCDATA);
+                self.switch_to(CharacterReference);
+            }
+            Scalar('<') => {
+                self.switch_to(RCDATALessThanSign);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_rawtext(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('<') => {
+                self.switch_to(RAWTEXTLessThanSign);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_script_data(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('<') => {
+                self.switch_to(ScriptDataLessThanSign);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_plaintext(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
     }
 }
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_tag_open(&mut self) {
         let codepoint = self.next_input_character();
         match codepoint {
@@ -104,7 +173,724 @@ impl TokenizerImpl {
         }
     }
 
-    pub(in crate::tokenizer) fn handle_tag_name(&mut self) {
+    pub(in crate::tokenizer) fn handle_tag_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('/') => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('>') => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar(ch) if ch.is_ascii_uppercase() => {
+                self.current_tag_token()
+                    .tag_name
+                    .push(ch.to_ascii_lowercase());
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_tag_token()
+                    .tag_name
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(ch) => {
+                self.current_tag_token().tag_name.push(ch);
+            }
+        }
+    }
+}
+
+impl<S: ErrorSink> TokenizerImpl<S> {
+    pub(in crate::tokenizer) fn handle_rcdata_less_than_sign(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('/') => {
+                self.temporary_buffer.clear();
+                self.switch_to(RCDATAEndTagOpen);
+            }
+            _ => {
+                self.emit_character('<');
+                self.reconsume_in(RCDATA);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_rcdata_end_tag_open(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.create_new_end_tag_token();
+                self.reconsume_in(RCDATAEndTagName);
+            }
+            _ => {
+                self.emit_character('<');
+                self.emit_character('/');
+                self.reconsume_in(RCDATA);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_rcdata_end_tag_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ')
+                if self.is_appropriate_end_tag_token() =>
+            {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('/') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('>') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.current_tag_token()
+                    .tag_name
+                    .push(ch.to_ascii_lowercase());
+                self.temporary_buffer.push(ch);
+            }
+            _ => {
+                self.abandon_end_tag_token();
+                self.reconsume_in(RCDATA);
+            }
+        }
+    }
+}
+
+impl<S: ErrorSink> TokenizerImpl<S> {
+    pub(in crate::tokenizer) fn handle_rawtext_less_than_sign(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('/') => {
+                self.temporary_buffer.clear();
+                self.switch_to(RAWTEXTEndTagOpen);
+            }
+            _ => {
+                self.emit_character('<');
+                self.reconsume_in(RAWTEXT);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_rawtext_end_tag_open(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.create_new_end_tag_token();
+                self.reconsume_in(RAWTEXTEndTagName);
+            }
+            _ => {
+                self.emit_character('<');
+                self.emit_character('/');
+                self.reconsume_in(RAWTEXT);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_rawtext_end_tag_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ')
+                if self.is_appropriate_end_tag_token() =>
+            {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('/') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('>') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.current_tag_token()
+                    .tag_name
+                    .push(ch.to_ascii_lowercase());
+                self.temporary_buffer.push(ch);
+            }
+            _ => {
+                self.abandon_end_tag_token();
+                self.reconsume_in(RAWTEXT);
+            }
+        }
+    }
+}
+
+impl<S: ErrorSink> TokenizerImpl<S> {
+    pub(in crate::tokenizer) fn handle_script_data_less_than_sign(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('/') => {
+                self.temporary_buffer.clear();
+                self.switch_to(ScriptDataEndTagOpen);
+            }
+            Scalar('!') => {
+                self.switch_to(ScriptDataEscapeStart);
+                self.emit_character('<');
+                self.emit_character('!');
+            }
+            _ => {
+                self.emit_character('<');
+                self.reconsume_in(ScriptData);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_end_tag_open(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.create_new_end_tag_token();
+                self.reconsume_in(ScriptDataEndTagName);
+            }
+            _ => {
+                self.emit_character('<');
+                self.emit_character('/');
+                self.reconsume_in(ScriptData);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_end_tag_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ')
+                if self.is_appropriate_end_tag_token() =>
+            {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('/') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('>') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.current_tag_token()
+                    .tag_name
+                    .push(ch.to_ascii_lowercase());
+                self.temporary_buffer.push(ch);
+            }
+            _ => {
+                self.abandon_end_tag_token();
+                self.reconsume_in(ScriptData);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escape_start(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataEscapeStartDash);
+                self.emit_character('-');
+            }
+            _ => {
+                self.reconsume_in(ScriptData);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escape_start_dash(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataEscapedDashDash);
+                self.emit_character('-');
+            }
+            _ => {
+                self.reconsume_in(ScriptData);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataEscapedDash);
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataEscapedLessThanSign);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped_dash(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataEscapedDashDash);
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataEscapedLessThanSign);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.switch_to(ScriptDataEscaped);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.switch_to(ScriptDataEscaped);
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped_dash_dash(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataEscapedLessThanSign);
+            }
+            Scalar('>') => {
+                self.switch_to(ScriptData);
+                self.emit_character('>');
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.switch_to(ScriptDataEscaped);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.switch_to(ScriptDataEscaped);
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped_less_than_sign(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('/') => {
+                self.temporary_buffer.clear();
+                self.switch_to(ScriptDataEscapedEndTagOpen);
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.temporary_buffer.clear();
+                self.emit_character('<');
+                self.reconsume_in(ScriptDataDoubleEscapeStart);
+            }
+            _ => {
+                self.emit_character('<');
+                self.reconsume_in(ScriptDataEscaped);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped_end_tag_open(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.create_new_end_tag_token();
+                self.reconsume_in(ScriptDataEscapedEndTagName);
+            }
+            _ => {
+                self.emit_character('<');
+                self.emit_character('/');
+                self.reconsume_in(ScriptDataEscaped);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_escaped_end_tag_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ')
+                if self.is_appropriate_end_tag_token() =>
+            {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('/') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('>') if self.is_appropriate_end_tag_token() => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.current_tag_token()
+                    .tag_name
+                    .push(ch.to_ascii_lowercase());
+                self.temporary_buffer.push(ch);
+            }
+            _ => {
+                self.abandon_end_tag_token();
+                self.reconsume_in(ScriptDataEscaped);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escape_start(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') | Scalar('/')
+            | Scalar('>') => {
+                if self.temporary_buffer == "script" {
+                    self.switch_to(ScriptDataDoubleEscaped);
+                } else {
+                    self.switch_to(ScriptDataEscaped);
+                }
+                self.emit_current_input_character();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.temporary_buffer.push(ch.to_ascii_lowercase());
+                self.emit_current_input_character();
+            }
+            _ => {
+                self.reconsume_in(ScriptDataEscaped);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escaped(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataDoubleEscapedDash);
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataDoubleEscapedLessThanSign);
+                self.emit_character('<');
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escaped_dash(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.switch_to(ScriptDataDoubleEscapedDashDash);
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataDoubleEscapedLessThanSign);
+                self.emit_character('<');
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.switch_to(ScriptDataDoubleEscaped);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.switch_to(ScriptDataDoubleEscaped);
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escaped_dash_dash(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('-') => {
+                self.emit_character('-');
+            }
+            Scalar('<') => {
+                self.switch_to(ScriptDataDoubleEscapedLessThanSign);
+                self.emit_character('<');
+            }
+            Scalar('>') => {
+                self.switch_to(ScriptData);
+                self.emit_character('>');
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.switch_to(ScriptDataDoubleEscaped);
+                self.emit_character(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInScriptHtmlCommentLikeText);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.switch_to(ScriptDataDoubleEscaped);
+                self.emit_current_input_character();
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escaped_less_than_sign(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('/') => {
+                self.temporary_buffer.clear();
+                self.switch_to(ScriptDataDoubleEscapeEnd);
+                self.emit_character('/');
+            }
+            _ => {
+                self.reconsume_in(ScriptDataDoubleEscaped);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_script_data_double_escape_end(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') | Scalar('/')
+            | Scalar('>') => {
+                if self.temporary_buffer == "script" {
+                    self.switch_to(ScriptDataEscaped);
+                } else {
+                    self.switch_to(ScriptDataDoubleEscaped);
+                }
+                self.emit_current_input_character();
+            }
+            Scalar(ch) if ch.is_ascii_alphabetic() => {
+                self.temporary_buffer.push(ch.to_ascii_lowercase());
+                self.emit_current_input_character();
+            }
+            _ => {
+                self.reconsume_in(ScriptDataDoubleEscaped);
+            }
+        }
+    }
+}
+
+impl<S: ErrorSink> TokenizerImpl<S> {
+    pub(in crate::tokenizer) fn handle_before_attribute_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
+                // ignore the character
+            }
+            Scalar('/') | Scalar('>') | EndOfFile => {
+                self.reconsume_in(AfterAttributeName);
+            }
+            Scalar('=') => {
+                self.emit_parse_error(UnexpectedEqualsSignBeforeAttributeName);
+                self.start_new_attribute();
+                self.current_attribute().name_mut().push('=');
+                self.switch_to(AttributeName);
+            }
+            Scalar(_) => {
+                self.start_new_attribute();
+                self.reconsume_in(AttributeName);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_attribute_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') | Scalar('/')
+            | Scalar('>') | EndOfFile => {
+                self.leave_attribute_name_state();
+                self.reconsume_in(AfterAttributeName);
+            }
+            Scalar('=') => {
+                self.leave_attribute_name_state();
+                self.switch_to(BeforeAttributeValue);
+            }
+            Scalar(ch) if ch.is_ascii_uppercase() => {
+                self.current_attribute()
+                    .name_mut()
+                    .push(ch.to_ascii_lowercase());
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_attribute()
+                    .name_mut()
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            Scalar(ch) => {
+                if ch == '"' || ch == '\'' || ch == '<' {
+                    self.emit_parse_error(UnexpectedCharacterInAttributeName);
+                }
+                self.current_attribute().name_mut().push(ch);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_after_attribute_name(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
+                // ignore the character
+            }
+            Scalar('/') => {
+                self.switch_to(SelfClosingStartTag);
+            }
+            Scalar('=') => {
+                self.switch_to(BeforeAttributeValue);
+            }
+            Scalar('>') => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.start_new_attribute();
+                self.reconsume_in(AttributeName);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_before_attribute_value(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
+                // ignore the character
+            }
+            Scalar('"') => {
+                self.switch_to(AttributeValueDoubleQuoted);
+            }
+            Scalar('\'') => {
+                self.switch_to(AttributeValueSingleQuoted);
+            }
+            Scalar('>') => {
+                self.emit_parse_error(MissingAttributeValue);
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            _ => {
+                self.reconsume_in(AttributeValueUnquoted);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_attribute_value_double_quoted(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('"') => {
+                self.switch_to(AfterAttributeValueQuoted);
+            }
+            Scalar('&') => {
+                debug_assert_eq!(self.return_state, None);
+                self.return_state = Some(AttributeValueDoubleQuoted);
+                self.switch_to(CharacterReference);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_attribute()
+                    .value_mut()
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(ch) => {
+                self.current_attribute().value_mut().push(ch);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_attribute_value_single_quoted(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\'') => {
+                self.switch_to(AfterAttributeValueQuoted);
+            }
+            Scalar('&') => {
+                debug_assert_eq!(self.return_state, None);
+                self.return_state = Some(AttributeValueSingleQuoted);
+                self.switch_to(CharacterReference);
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_attribute()
+                    .value_mut()
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(ch) => {
+                self.current_attribute().value_mut().push(ch);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_attribute_value_unquoted(&mut self) {
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
+                self.switch_to(BeforeAttributeName);
+            }
+            Scalar('&') => {
+                debug_assert_eq!(self.return_state, None);
+                self.return_state = Some(AttributeValueUnquoted);
+                self.switch_to(CharacterReference);
+            }
+            Scalar('>') => {
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_attribute()
+                    .value_mut()
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(ch) => {
+                if ch == '"' || ch == '\'' || ch == '<' || ch == '=' || ch == '`' {
+                    self.emit_parse_error(UnexpectedCharacterInUnquotedAttributeValue);
+                }
+                self.current_attribute().value_mut().push(ch);
+            }
+        }
+    }
+
+    pub(in crate::tokenizer) fn handle_after_attribute_value_quoted(&mut self) {
         let codepoint = self.next_input_character();
         match codepoint {
             Scalar('\t') | Scalar('\n') | Scalar('\u{000C}') | Scalar(' ') => {
@@ -117,169 +903,61 @@ impl TokenizerImpl {
                 self.switch_to(Data);
                 self.emit_current_tag_token();
             }
-            Scalar(ch) if ch.is_ascii_uppercase() => {
-                self.current_tag_token()
-                    .tag_name
-                    .push(ch.to_ascii_lowercase());
-            }
-            Scalar('\0') => {
-                self.emit_parse_error(UnexpectedNullCharacter);
-                self.current_tag_token()
-                    .tag_name
-                    .push(REPLACEMENT_CHARACTER);
-            }
             EndOfFile => {
                 self.emit_parse_error(EofInTag);
                 self.emit_eof();
             }
-            Scalar(ch) => {
-                self.current_tag_token().tag_name.push(ch);
+            Scalar(_) => {
+                self.emit_parse_error(MissingWhitespaceBetweenAttributes);
+                self.reconsume_in(BeforeAttributeName);
             }
         }
     }
 }
 
-impl TokenizerImpl {
-    pub(in crate::tokenizer) fn handle_rcdata_less_than_sign(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_rcdata_end_tag_open(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_rcdata_end_tag_name(&mut self) {
-        unimplemented!();
-    }
-}
-
-impl TokenizerImpl {
-    pub(in crate::tokenizer) fn handle_rawtext_less_than_sign(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_rawtext_end_tag_open(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_rawtext_end_tag_name(&mut self) {
-        unimplemented!();
-    }
-}
-
-impl TokenizerImpl {
-    pub(in crate::tokenizer) fn handle_script_data_less_than_sign(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_end_tag_open(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_end_tag_name(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escape_start(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escape_start_dash(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped_dash(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped_dash_dash(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped_less_than_sign(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped_end_tag_open(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_escaped_end_tag_name(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escape_start(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escaped(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escaped_dash(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escaped_dash_dash(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escaped_less_than_sign(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_script_data_double_escape_end(&mut self) {
-        unimplemented!();
-    }
-}
-
-impl TokenizerImpl {
-    pub(in crate::tokenizer) fn handle_before_attribute_name(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_attribute_name(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_after_attribute_name(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_before_attribute_value(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_attribute_value_double_quoted(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_attribute_value_single_quoted(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_attribute_value_unquoted(&mut self) {
-        unimplemented!();
-    }
-
-    pub(in crate::tokenizer) fn handle_after_attribute_value_quoted(&mut self) {
-        unimplemented!();
-    }
-}
-
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_self_closing_start_tag(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('>') => {
+                self.current_tag_token().self_closing = true;
+                self.switch_to(Data);
+                self.emit_current_tag_token();
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInTag);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_parse_error(UnexpectedSolidusInTag);
+                self.reconsume_in(BeforeAttributeName);
+            }
+        }
     }
 }
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_bogus_comment(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('>') => {
+                self.switch_to(Data);
+                self.emit_current_comment_token();
+            }
+            EndOfFile => {
+                self.emit_current_comment_token();
+                self.emit_eof();
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                self.current_comment_token()
+                    .data
+                    .push(REPLACEMENT_CHARACTER);
+            }
+            Scalar(ch) => {
+                self.current_comment_token().data.push(ch);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_markup_declaration_open(&mut self) {
@@ -298,7 +976,14 @@ impl TokenizerImpl {
             .maybe_consume_next_few_matching_characters("[CDATA[", true)
             .is_some()
         {
-            unimplemented!();
+            if self.in_foreign_content() {
+                self.switch_to(CDATASection);
+            } else {
+                self.emit_parse_error(CdataInHtmlContent);
+                self.create_new_comment_token();
+                self.current_comment_token().data.push_str("[CDATA[");
+                self.switch_to(BogusComment);
+            }
         } else {
             self.emit_parse_error(IncorrectlyOpenedComment);
             self.create_new_comment_token();
@@ -494,7 +1179,7 @@ impl TokenizerImpl {
     }
 }
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_doctype(&mut self) {
         let codepoint = self.next_input_character();
         match codepoint {
@@ -626,7 +1311,8 @@ impl TokenizerImpl {
                 } else {
                     self.emit_parse_error(InvalidCharacterSequenceAfterDoctypeName);
                     self.current_doctype_token().force_quirks = true;
-                    self.reconsume_in(BogusDOCTYPE);
+                    // the character was only peeked at, so it will be consumed by the next state
+                    self.switch_to(BogusDOCTYPE);
                 }
             }
         }
@@ -1006,58 +1692,259 @@ impl TokenizerImpl {
     }
 
     pub(in crate::tokenizer) fn handle_bogusdoctype(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar('>') => {
+                self.switch_to(Data);
+                self.emit_current_doctype_token();
+            }
+            Scalar('\0') => {
+                self.emit_parse_error(UnexpectedNullCharacter);
+                // ignore the character
+            }
+            EndOfFile => {
+                self.emit_current_doctype_token();
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                // ignore the character
+            }
+        }
     }
 }
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_cdata_section(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(']') => {
+                self.switch_to(CDATASectionBracket);
+            }
+            EndOfFile => {
+                self.emit_parse_error(EofInCdata);
+                self.emit_eof();
+            }
+            Scalar(_) => {
+                self.emit_current_input_character();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_cdata_section_bracket(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(']') => {
+                self.switch_to(CDATASectionEnd);
+            }
+            _ => {
+                self.emit_character(']');
+                self.reconsume_in(CDATASection);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_cdata_section_end(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(']') => {
+                self.emit_character(']');
+            }
+            Scalar('>') => {
+                self.switch_to(Data);
+            }
+            _ => {
+                self.emit_character(']');
+                self.emit_character(']');
+                self.reconsume_in(CDATASection);
+            }
+        }
     }
 }
 
-impl TokenizerImpl {
+impl<S: ErrorSink> TokenizerImpl<S> {
     pub(in crate::tokenizer) fn handle_character_reference(&mut self) {
-        unimplemented!();
+        self.temporary_buffer.clear();
+        self.temporary_buffer.push('&');
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphanumeric() => {
+                self.reconsume_in(NamedCharacterReference);
+            }
+            Scalar('#') => {
+                self.temporary_buffer.push('#');
+                self.switch_to(NumericCharacterReference);
+            }
+            _ => {
+                self.flush_code_points_consumed_as_a_character_reference();
+                self.reconsume_in_return_state();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_named_character_reference(&mut self) {
-        unimplemented!();
+        let mut matcher = NamedCharacterReferenceMatcher::new();
+        let mut offset = 0;
+        while let Scalar(ch) = self.peek_input_character(offset) {
+            offset += 1;
+            if !matcher.push(ch) {
+                break;
+            }
+        }
+
+        match matcher.longest_match() {
+            Some((name_len, characters)) => {
+                for _ in 0..name_len {
+                    let ch = self.next_input_character().try_into().unwrap();
+                    self.temporary_buffer.push(ch);
+                }
+                let terminated = self.temporary_buffer.ends_with(';');
+                let next_codepoint = self.peek_next_input_character();
+                if self.consumed_as_part_of_an_attribute()
+                    && !terminated
+                    && matches!(next_codepoint, Scalar(ch) if ch == '=' || ch.is_ascii_alphanumeric())
+                {
+                    // for historical reasons
+                    self.flush_code_points_consumed_as_a_character_reference();
+                    self.switch_to_return_state();
+                } else {
+                    if !terminated {
+                        self.emit_parse_error(MissingSemicolonAfterCharacterReference);
+                    }
+                    self.flush_character_reference(characters);
+                    self.switch_to_return_state();
+                }
+            }
+            None => {
+                self.flush_code_points_consumed_as_a_character_reference();
+                self.switch_to(AmbiguousAmpersand);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_ambiguous_ampersand(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_alphanumeric() => {
+                if self.consumed_as_part_of_an_attribute() {
+                    self.current_attribute().value_mut().push(ch);
+                } else {
+                    self.emit_current_input_character();
+                }
+            }
+            Scalar(';') => {
+                self.emit_parse_error(UnknownNamedCharacterReference);
+                self.reconsume_in_return_state();
+            }
+            _ => {
+                self.reconsume_in_return_state();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_numeric_character_reference(&mut self) {
-        unimplemented!();
+        self.character_reference_code = 0;
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch @ 'x') | Scalar(ch @ 'X') => {
+                self.temporary_buffer.push(ch);
+                self.switch_to(HexadecimalCharacterReferenceStart);
+            }
+            _ => {
+                self.reconsume_in(DecimalCharacterReferenceStart);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_hexadecimal_character_reference_start(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_hexdigit() => {
+                self.reconsume_in(HexadecimalCharacterReference);
+            }
+            _ => {
+                self.emit_parse_error(AbsenceOfDigitsInNumericCharacterReference);
+                self.flush_code_points_consumed_as_a_character_reference();
+                self.reconsume_in_return_state();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_decimal_character_reference_start(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_digit() => {
+                self.reconsume_in(DecimalCharacterReference);
+            }
+            _ => {
+                self.emit_parse_error(AbsenceOfDigitsInNumericCharacterReference);
+                self.flush_code_points_consumed_as_a_character_reference();
+                self.reconsume_in_return_state();
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_hexadecimal_character_reference(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_hexdigit() => {
+                let digit = ch.to_digit(16).unwrap();
+                self.character_reference_code = self
+                    .character_reference_code
+                    .saturating_mul(16)
+                    .saturating_add(digit);
+            }
+            Scalar(';') => {
+                self.switch_to(NumericCharacterReferenceEnd);
+            }
+            _ => {
+                self.emit_parse_error(MissingSemicolonAfterCharacterReference);
+                self.reconsume_in(NumericCharacterReferenceEnd);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_decimal_character_reference(&mut self) {
-        unimplemented!();
+        let codepoint = self.next_input_character();
+        match codepoint {
+            Scalar(ch) if ch.is_ascii_digit() => {
+                let digit = ch.to_digit(10).unwrap();
+                self.character_reference_code = self
+                    .character_reference_code
+                    .saturating_mul(10)
+                    .saturating_add(digit);
+            }
+            Scalar(';') => {
+                self.switch_to(NumericCharacterReferenceEnd);
+            }
+            _ => {
+                self.emit_parse_error(MissingSemicolonAfterCharacterReference);
+                self.reconsume_in(NumericCharacterReferenceEnd);
+            }
+        }
     }
 
     pub(in crate::tokenizer) fn handle_numeric_character_reference_end(&mut self) {
-        unimplemented!();
+        let mut code = self.character_reference_code;
+        if code == 0x00 {
+            self.emit_parse_error(NullCharacterReference);
+            code = REPLACEMENT_CHARACTER.into();
+        } else if code > 0x10FFFF {
+            self.emit_parse_error(CharacterReferenceOutsideUnicodeRange);
+            code = REPLACEMENT_CHARACTER.into();
+        } else if is_surrogate(code) {
+            self.emit_parse_error(SurrogateCharacterReference);
+            code = REPLACEMENT_CHARACTER.into();
+        } else if is_noncharacter(code) {
+            self.emit_parse_error(NoncharacterCharacterReference);
+        } else if code == 0x0D || (is_control(code) && !is_ascii_whitespace(code)) {
+            self.emit_parse_error(ControlCharacterReference);
+            if let Some(replacement) = numeric_character_reference_replacement(code) {
+                code = replacement;
+            }
+        }
+
+        let ch = char::from_u32(code).unwrap_or(REPLACEMENT_CHARACTER);
+        self.flush_character_reference(ch.encode_utf8(&mut [0; 4]));
+        self.switch_to_return_state();
     }
 }
@@ -1,15 +1,22 @@
 use std::collections::VecDeque;
 use std::convert::TryInto;
 
+use crate::dom::Namespace;
+use crate::error::Error;
+use crate::sink::{ErrorSink, FailFast};
 use error::ParseError;
 use result::Result;
+use span::{SourcePosition, Span, Spanned};
+pub use state::TokenizerState;
 use state::*;
 pub use token::*;
 use util::*;
 
 pub mod error;
 pub mod result;
+pub mod span;
 
+mod entities;
 mod impl_;
 mod state;
 mod token;
@@ -18,40 +25,81 @@ mod util;
 #[cfg(test)]
 mod tests;
 
-pub trait Tokenizer: Iterator<Item = Result<Token>> {}
+/// Yields each token along with the part of the source text it came from, interleaved with any parse
+/// errors. The methods the tree builder calls do nothing by default, which suits a tokenizer whose
+/// tokens are known up front.
+pub trait Tokenizer: Iterator<Item = Result<Spanned<Token>>> {
+    /// Called by the tree builder after it has received certain start tags (eg. `<title>`), so the
+    /// text which follows is tokenized appropriately. Takes effect from the next token.
+    fn set_state(&mut self, _state: TokenizerState) {}
+
+    /// Overrides the name of the last start tag to have been emitted, which determines whether an
+    /// end tag is appropriate for closing RCDATA, RAWTEXT and script data
+    fn set_last_start_tag(&mut self, _tag_name: Option<&str>) {}
+
+    /// Called by the tree builder before each token, so the tokenizer knows whether CDATA sections
+    /// are allowed: they are only recognised in foreign (SVG and MathML) content. `None` if there is
+    /// no adjusted current node.
+    fn set_adjusted_current_node_namespace(&mut self, _namespace: Option<Namespace>) {}
+}
 
-pub struct TokenizerImpl {
+pub struct TokenizerImpl<S: ErrorSink> {
     input: CodepointStream,
-    output_buf: VecDeque<Result<Token>>,
+    error_sink: S,
+    output_buf: VecDeque<Result<Spanned<Token>>>,
     finished: bool,
     state: State,
     return_state: Option<State>,
     current_input_character: Codepoint,
+    current_input_character_start: SourcePosition,
     reconsume_next_input_character: bool,
+    /// Where the source text of the next token to be emitted begins
+    token_start: SourcePosition,
+    temporary_buffer: String,
+    character_reference_code: u32,
     current_doctype_token: Option<IncompleteDoctype>,
     current_tag_token: Option<IncompleteTag>,
     current_comment_token: Option<IncompleteComment>,
+    last_start_tag_name: Option<String>,
+    adjusted_current_node_namespace: Option<Namespace>,
 }
 
-impl TokenizerImpl {
-    pub fn new(input: String) -> Self {
+impl<S: ErrorSink> TokenizerImpl<S> {
+    /// Parse errors are reported to the `error_sink`, and only returned by the iterator if the sink
+    /// stops tokenization
+    pub fn new(input: String, error_sink: S) -> Self {
         Self {
             input: CodepointStream::from(input),
+            error_sink,
             output_buf: VecDeque::with_capacity(4),
             finished: false,
             state: State::Data,
             return_state: None,
             current_input_character: Codepoint::NULL,
+            current_input_character_start: SourcePosition::START,
             reconsume_next_input_character: false,
+            token_start: SourcePosition::START,
+            temporary_buffer: String::new(),
+            character_reference_code: 0,
             current_doctype_token: None,
             current_tag_token: None,
             current_comment_token: None,
+            last_start_tag_name: None,
+            adjusted_current_node_namespace: None,
         }
     }
 }
 
-impl Iterator for TokenizerImpl {
-    type Item = Result<Token>;
+impl TokenizerImpl<FailFast> {
+    /// Creates a tokenizer which stops at the first parse error, returning it in place of the next
+    /// token
+    pub fn strict(input: String) -> Self {
+        Self::new(input, FailFast::new())
+    }
+}
+
+impl<S: ErrorSink> Iterator for TokenizerImpl<S> {
+    type Item = Result<Spanned<Token>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if !self.finished {
@@ -62,9 +110,16 @@ impl Iterator for TokenizerImpl {
                     break;
                 }
                 self.do_some_work();
+                self.assign_token_spans();
             }
             let output = output.unwrap();
-            if output == Ok(Token::EndOfFile) {
+            if matches!(
+                output,
+                Ok(Spanned {
+                    value: Token::EndOfFile,
+                    ..
+                }) | Err(_)
+            ) {
                 self.finished = true;
             }
             Some(output)
@@ -74,10 +129,22 @@ impl Iterator for TokenizerImpl {
     }
 }
 
-impl Tokenizer for TokenizerImpl {}
+impl<S: ErrorSink> Tokenizer for TokenizerImpl<S> {
+    fn set_state(&mut self, state: TokenizerState) {
+        self.switch_to(state.into());
+    }
+
+    fn set_last_start_tag(&mut self, tag_name: Option<&str>) {
+        self.last_start_tag_name = tag_name.map(String::from);
+    }
+
+    fn set_adjusted_current_node_namespace(&mut self, namespace: Option<Namespace>) {
+        self.adjusted_current_node_namespace = namespace;
+    }
+}
 
-impl TokenizerImpl {
-    fn peek_input_character(&self, offset: usize) -> Codepoint {
+impl<S: ErrorSink> TokenizerImpl<S> {
+    pub(in crate::tokenizer) fn peek_input_character(&self, offset: usize) -> Codepoint {
         if self.reconsume_next_input_character {
             if offset == 0 {
                 self.current_input_character
@@ -101,23 +168,18 @@ impl TokenizerImpl {
 
     pub(in crate::tokenizer) fn next_input_character(&mut self) -> Codepoint {
         if !self.reconsume_next_input_character {
+            self.current_input_character_start = self.input.position();
             self.current_input_character = self.input.consume_next();
+            if let Codepoint::Scalar(ch) = self.current_input_character {
+                if let Some(error) = input_stream_error(ch) {
+                    self.emit_parse_error(error);
+                }
+            }
         }
         self.reconsume_next_input_character = false;
         self.current_input_character
     }
 
-    pub(in crate::tokenizer) fn next_few_characters_match(
-        &self,
-        expected: &str,
-        case_sensitive: bool,
-    ) -> bool {
-        expected.chars().enumerate().all(|(idx, expected_ch)| {
-            self.peek_input_character(idx)
-                .eq_char(&expected_ch, case_sensitive)
-        })
-    }
-
     pub(in crate::tokenizer) fn maybe_consume_next_few_matching_characters(
         &mut self,
         expected: &str,
@@ -139,9 +201,10 @@ impl TokenizerImpl {
     }
 
     pub(in crate::tokenizer) fn reconsume_in(&mut self, next_state: State) {
-        println!(
+        trace!(
             "Tokenizer::reconsume_in: {:?} -> {:?}",
-            self.state, next_state
+            self.state,
+            next_state
         );
         debug_assert!(!self.reconsume_next_input_character);
         self.reconsume_next_input_character = true;
@@ -152,26 +215,127 @@ impl TokenizerImpl {
         if self.state == next_state {
             return;
         }
-        println!("Tokenizer::switch_to: {:?} -> {:?}", self.state, next_state);
+        trace!("Tokenizer::switch_to: {:?} -> {:?}", self.state, next_state);
         self.state = next_state;
     }
 
+    pub(in crate::tokenizer) fn reconsume_in_return_state(&mut self) {
+        let return_state = self.return_state.take().unwrap();
+        self.reconsume_in(return_state);
+    }
+
+    pub(in crate::tokenizer) fn switch_to_return_state(&mut self) {
+        let return_state = self.return_state.take().unwrap();
+        self.switch_to(return_state);
+    }
+
+    pub(in crate::tokenizer) fn in_foreign_content(&self) -> bool {
+        matches!(&self.adjusted_current_node_namespace, Some(namespace) if *namespace != Namespace::Html)
+    }
+
+    pub(in crate::tokenizer) fn consumed_as_part_of_an_attribute(&self) -> bool {
+        matches!(
+            self.return_state,
+            Some(State::AttributeValueDoubleQuoted)
+                | Some(State::AttributeValueSingleQuoted)
+                | Some(State::AttributeValueUnquoted)
+        )
+    }
+
+    pub(in crate::tokenizer) fn flush_code_points_consumed_as_a_character_reference(&mut self) {
+        let buf = std::mem::take(&mut self.temporary_buffer);
+        if self.consumed_as_part_of_an_attribute() {
+            self.current_attribute().value_mut().push_str(&buf);
+        } else {
+            buf.chars().for_each(|ch| self.emit_character(ch));
+        }
+    }
+
+    /// Like `flush_code_points_consumed_as_a_character_reference`, but for the characters the
+    /// reference stands for rather than its source text, so they all share the reference's span
+    pub(in crate::tokenizer) fn flush_character_reference(&mut self, characters: &str) {
+        self.temporary_buffer.clear();
+        if self.consumed_as_part_of_an_attribute() {
+            self.current_attribute().value_mut().push_str(characters);
+        } else {
+            for ch in characters.chars() {
+                self.emit_token(Token::Character(ch));
+            }
+        }
+    }
+
+    /// Emits a character token standing for the next character of the source text (eg. the `<` of
+    /// a `<` which doesn't begin a tag), which is where the token's span starts
     pub(in crate::tokenizer) fn emit_character(&mut self, data: char) {
+        let start = self.token_start;
         self.emit_token(Token::Character(data));
+        if let Some((ch, len)) = self.input.character_at(start) {
+            self.token_start = start.after(ch, len);
+        }
     }
 
     pub(in crate::tokenizer) fn emit_eof(&mut self) {
         self.emit_token(Token::EndOfFile);
     }
 
+    fn emit_incomplete_token(&mut self, incomplete_token: impl IncompleteToken) {
+        self.emit_token(incomplete_token.into());
+    }
+
+    /// The token's span starts where its source text does, and is ended once the current character
+    /// has been dealt with
     fn emit_token(&mut self, token: Token) {
-        println!("Tokenizer::emit_token: {:?}", token);
-        self.output_buf.push_back(Ok(token));
+        trace!("Tokenizer::emit_token: {:?}", token);
+        let span = Span::new(self.token_start, self.token_start);
+        self.output_buf.push_back(Ok(Spanned::new(token, span)));
     }
 
+    /// The error's span is that of the current input character. If the error sink stops
+    /// tokenization, the error is returned in place of any more tokens.
     pub(in crate::tokenizer) fn emit_parse_error(&mut self, error: ParseError) {
-        println!("Tokenizer::emit_parse_error: {:?}", error);
-        self.output_buf.push_back(Err(error));
+        trace!("Tokenizer::emit_parse_error: {:?}", error);
+        let span = Span::new(self.current_input_character_start, self.input.position());
+        let error = Spanned::new(error, span);
+        if self.error_sink.report(error.map(Error::from)).is_break() {
+            self.output_buf.push_back(Err(error));
+        }
+    }
+
+    /// Each token emitted while dealing with the current input character spans up to where the next
+    /// one starts. The last one (along with any which start at the same place, like the characters
+    /// a reference stands for) spans up to and including the current input character, unless it's
+    /// going to be reconsumed. Source text which doesn't end up in any token (eg. `</>`) is skipped
+    /// over.
+    fn assign_token_spans(&mut self) {
+        let end = if self.reconsume_next_input_character {
+            self.current_input_character_start
+        } else {
+            self.input.position()
+        };
+
+        let mut emitted_any = false;
+        let mut token_end = end;
+        let mut next_start = end;
+        for token in self.output_buf.iter_mut().flatten().rev() {
+            if token.span.start != next_start {
+                token_end = next_start;
+            }
+            next_start = token.span.start;
+            token.span.end = token_end;
+            emitted_any = true;
+        }
+        let between_tokens = matches!(
+            self.state,
+            State::Data
+                | State::RCDATA
+                | State::RAWTEXT
+                | State::ScriptData
+                | State::PLAINTEXT
+                | State::CDATASection
+        );
+        if emitted_any || between_tokens {
+            self.token_start = end;
+        }
     }
 
     pub(in crate::tokenizer) fn create_new_doctype_token(&mut self) {
@@ -185,7 +349,7 @@ impl TokenizerImpl {
 
     pub(in crate::tokenizer) fn emit_current_doctype_token(&mut self) {
         let incomplete_token = self.current_doctype_token.take().unwrap();
-        self.emit_token(incomplete_token.into());
+        self.emit_incomplete_token(incomplete_token);
     }
 
     pub(in crate::tokenizer) fn create_new_start_tag_token(&mut self) {
@@ -203,8 +367,53 @@ impl TokenizerImpl {
     }
 
     pub(in crate::tokenizer) fn emit_current_tag_token(&mut self) {
-        let incomplete_token = self.current_tag_token.take().unwrap();
-        self.emit_token(incomplete_token.into());
+        let mut incomplete_token = self.current_tag_token.take().unwrap();
+        incomplete_token.finish_current_attribute();
+        match incomplete_token.kind {
+            TagKind::Start => {
+                self.last_start_tag_name = Some(incomplete_token.tag_name.clone());
+            }
+            TagKind::End => {
+                if !incomplete_token.attributes.is_empty() {
+                    self.emit_parse_error(ParseError::EndTagWithAttributes);
+                }
+                if incomplete_token.self_closing {
+                    self.emit_parse_error(ParseError::EndTagWithTrailingSolidus);
+                }
+            }
+        }
+        self.emit_incomplete_token(incomplete_token);
+    }
+
+    /// An appropriate end tag token is an end tag token whose tag name matches the tag name of the
+    /// last start tag to have been emitted from this tokenizer, if any
+    pub(in crate::tokenizer) fn is_appropriate_end_tag_token(&self) -> bool {
+        let tag_token = self.current_tag_token.as_ref().unwrap();
+        debug_assert_eq!(tag_token.kind, TagKind::End);
+        self.last_start_tag_name.as_ref() == Some(&tag_token.tag_name)
+    }
+
+    /// Throws away the current (end) tag token, and emits the characters it was made of instead
+    pub(in crate::tokenizer) fn abandon_end_tag_token(&mut self) {
+        self.current_tag_token = None;
+        self.emit_character('<');
+        self.emit_character('/');
+        let buf = std::mem::take(&mut self.temporary_buffer);
+        buf.chars().for_each(|ch| self.emit_character(ch));
+    }
+
+    pub(in crate::tokenizer) fn start_new_attribute(&mut self) {
+        self.current_tag_token().start_new_attribute();
+    }
+
+    pub(in crate::tokenizer) fn current_attribute(&mut self) -> &mut Attribute {
+        self.current_tag_token().current_attribute()
+    }
+
+    pub(in crate::tokenizer) fn leave_attribute_name_state(&mut self) {
+        if !self.current_tag_token().check_current_attribute_name() {
+            self.emit_parse_error(ParseError::DuplicateAttribute);
+        }
     }
 
     pub(in crate::tokenizer) fn create_new_comment_token(&mut self) {
@@ -218,7 +427,7 @@ impl TokenizerImpl {
 
     pub(in crate::tokenizer) fn emit_current_comment_token(&mut self) {
         let incomplete_token = self.current_comment_token.take().unwrap();
-        self.emit_token(incomplete_token.into());
+        self.emit_incomplete_token(incomplete_token);
     }
 
     pub(in crate::tokenizer) fn emit_current_input_character(&mut self) {
@@ -1,3 +1,4 @@
 use super::error::ParseError;
+use super::span::Spanned;
 
-pub type Result<T> = std::result::Result<T, ParseError>;
+pub type Result<T> = std::result::Result<T, Spanned<ParseError>>;
@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// A position in the source text, just before some character
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SourcePosition {
+    /// Starts at 1. Each CR LF pair, lone CR and LF begins a new line.
+    pub line: usize,
+    /// Starts at 1, and counts characters rather than bytes
+    pub column: usize,
+    /// Starts at 0, and counts bytes of the (UTF-8) source text
+    pub offset: usize,
+}
+
+/// The part of the source text which something came from: from the `start` (inclusive) to the `end`
+/// (exclusive)
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Span {
+    pub start: SourcePosition,
+    pub end: SourcePosition,
+}
+
+/// Something which came from a particular part of the source text
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl SourcePosition {
+    pub const START: Self = Self {
+        line: 1,
+        column: 1,
+        offset: 0,
+    };
+
+    /// Returns the position just after the given character
+    pub(crate) fn after(self, ch: char, len: usize) -> Self {
+        if ch == '\n' {
+            Self {
+                line: self.line + 1,
+                column: 1,
+                offset: self.offset + len,
+            }
+        } else {
+            Self {
+                line: self.line,
+                column: self.column + 1,
+                offset: self.offset + len,
+            }
+        }
+    }
+}
+
+impl Default for SourcePosition {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl Display for SourcePosition {
+    //! ```
+    //! use dominoes::tokenizer::span::SourcePosition;
+    //! let position = SourcePosition { line: 3, column: 14, offset: 52 };
+    //! assert_eq!(format!("{}", position), "3:14");
+    //! ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Span {
+    pub fn new(start: SourcePosition, end: SourcePosition) -> Self {
+        debug_assert!(start <= end);
+        Self { start, end }
+    }
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned::new(f(self.value), self.span)
+    }
+}
+
+impl<T: Error> Error for Spanned<T> {}
+
+impl<T: Display> Display for Spanned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.value, self.span.start)
+    }
+}
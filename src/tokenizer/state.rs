@@ -1,5 +1,21 @@
+/// The states which the tree builder may switch a [`Tokenizer`](super::Tokenizer) into. Each
+/// determines how the text following a start tag is tokenized.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum TokenizerState {
+    Data,
+    /// Text with character references but no tags, eg. the content of `<title>` and `<textarea>`
+    RCDATA,
+    /// Text without character references or tags, eg. the content of `<style>` and `<xmp>`
+    RAWTEXT,
+    /// The content of a `<script>`
+    ScriptData,
+    /// Everything up until the end of the input, after a `<plaintext>`
+    PLAINTEXT,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 pub(crate) enum State {
     Data,
     RCDATA,
@@ -82,3 +98,15 @@ pub(crate) enum State {
     DecimalCharacterReference,
     NumericCharacterReferenceEnd,
 }
+
+impl From<TokenizerState> for State {
+    fn from(state: TokenizerState) -> Self {
+        match state {
+            TokenizerState::Data => Self::Data,
+            TokenizerState::RCDATA => Self::RCDATA,
+            TokenizerState::RAWTEXT => Self::RAWTEXT,
+            TokenizerState::ScriptData => Self::ScriptData,
+            TokenizerState::PLAINTEXT => Self::PLAINTEXT,
+        }
+    }
+}
@@ -1,25 +1,34 @@
-use super::error::ParseError::*;
+use crate::dom::Namespace;
+use crate::error::Error;
+use crate::sink::{FailFast, Ignore};
+
+use super::error::ParseError::{self, *};
+use super::result::Result;
+use super::span::{SourcePosition, Span, Spanned};
 use super::token::Token::*;
-use super::token::{Attributes, TagKind};
-use super::TokenizerImpl;
+use super::token::{Attributes, TagKind, Token};
+use super::{Tokenizer, TokenizerImpl, TokenizerState};
 
 #[test]
 fn empty_document() {
     let input = "";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [Ok(EndOfFile)];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn empty_html_tags() {
     let input = "<html></html>";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Tag {
@@ -37,14 +46,16 @@ fn empty_html_tags() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn dtd_less_doctype_decl() {
     let input = "<!DOCTYPE html>";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Doctype {
@@ -56,14 +67,16 @@ fn dtd_less_doctype_decl() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn doctype_decl_with_legacy_public_identifier() {
     let input = "<!DOCTYPE html PUBLIC \"my 'public' identifier\">";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Doctype {
@@ -75,14 +88,16 @@ fn doctype_decl_with_legacy_public_identifier() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn doctype_decl_with_legacy_system_identifier() {
     let input = "<!DOCTYPE html SYSTEM \"my 'system' identifier\">";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Doctype {
@@ -94,14 +109,16 @@ fn doctype_decl_with_legacy_system_identifier() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn doctype_decl_with_legacy_public_and_system_identifiers() {
     let input = "<!DOCTYPE html PUBLIC \"my 'public' identifier\" \"my 'system' identifier\">";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Doctype {
@@ -113,14 +130,16 @@ fn doctype_decl_with_legacy_public_and_system_identifiers() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn comment() {
     let input = "<!-- This - is -- a -> comment! -->";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
         Ok(Comment {
@@ -129,21 +148,830 @@ fn comment() {
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
 }
 
 #[test]
 fn nested_comment_parse_error() {
     let input = "<!-- This is a <!-- nested comment -->";
-    let tokenizer = TokenizerImpl::new(input.into());
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
 
-    let actual: Vec<_> = tokenizer.collect();
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
 
     let expected = [
-        Err(NestedComment),
         Ok(Comment {
             data: String::from(" This is a <!-- nested comment "),
         }),
         Ok(EndOfFile),
     ];
     assert_eq!(actual, &expected);
+    assert_eq!(errors, [NestedComment]);
+}
+
+#[test]
+fn named_character_reference() {
+    let input = "a&amp;b";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('a')),
+        Ok(Character('&')),
+        Ok(Character('b')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn legacy_named_character_reference_without_semicolon() {
+    let input = "&copy 2020";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('\u{A9}')),
+        Ok(Character(' ')),
+        Ok(Character('2')),
+        Ok(Character('0')),
+        Ok(Character('2')),
+        Ok(Character('0')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [MissingSemicolonAfterCharacterReference]);
+}
+
+#[test]
+fn unknown_named_character_reference() {
+    let input = "&x;";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('&')),
+        Ok(Character('x')),
+        Ok(Character(';')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [UnknownNamedCharacterReference]);
+}
+
+#[test]
+fn numeric_character_references() {
+    let input = "&#65;&#x42;&#X63";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('A')),
+        Ok(Character('B')),
+        Ok(Character('c')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [MissingSemicolonAfterCharacterReference]);
+}
+
+#[test]
+fn numeric_character_reference_without_digits() {
+    let input = "&#;";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('&')),
+        Ok(Character('#')),
+        Ok(Character(';')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [AbsenceOfDigitsInNumericCharacterReference]);
+}
+
+#[test]
+fn invalid_numeric_character_references() {
+    let input = "&#0;&#x110000;&#xD800;&#x80;";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('\u{FFFD}')),
+        Ok(Character('\u{FFFD}')),
+        Ok(Character('\u{FFFD}')),
+        Ok(Character('\u{20AC}')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            NullCharacterReference,
+            CharacterReferenceOutsideUnicodeRange,
+            SurrogateCharacterReference,
+            ControlCharacterReference
+        ]
+    );
+}
+
+#[test]
+fn named_character_reference_longest_prefix_match() {
+    let input = "&notit; &notin;";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('\u{AC}')),
+        Ok(Character('i')),
+        Ok(Character('t')),
+        Ok(Character(';')),
+        Ok(Character(' ')),
+        Ok(Character('\u{2209}')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [MissingSemicolonAfterCharacterReference]);
+}
+
+#[test]
+fn named_character_reference_with_multiple_codepoints() {
+    let input = "&NotEqualTilde;";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('\u{2242}')),
+        Ok(Character('\u{0338}')),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn start_tag_with_attributes() {
+    let input = "<a href=\"/x?a=1&amp;b=2\" id=main class='big'>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Tag {
+            kind: TagKind::Start,
+            tag_name: String::from("a"),
+            self_closing: false,
+            attributes: vec![("href", "/x?a=1&b=2"), ("id", "main"), ("class", "big")]
+                .into_iter()
+                .collect(),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn duplicate_attributes_are_dropped() {
+    let input = "<p ID=1 class=a id=2>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let attributes = match tokenizer.next().map(without_span) {
+        Some(Ok(Tag { attributes, .. })) => attributes,
+        token => panic!("Expected a tag but was: {:?}", token),
+    };
+    let attributes: Vec<_> = attributes
+        .iter()
+        .map(|attr| (attr.name(), attr.value()))
+        .collect();
+    assert_eq!(attributes, [("id", "1"), ("class", "a")]);
+    assert_eq!(errors, [DuplicateAttribute]);
+}
+
+#[test]
+fn legacy_named_character_reference_in_attribute_value() {
+    let input = "<a href=\"?x&not=1&not;\">";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Tag {
+            kind: TagKind::Start,
+            tag_name: String::from("a"),
+            self_closing: false,
+            attributes: vec![("href", "?x&not=1\u{AC}")].into_iter().collect(),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn attribute_parse_errors() {
+    let input = "<div a=\"b\"c=d e=`>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Tag {
+            kind: TagKind::Start,
+            tag_name: String::from("div"),
+            self_closing: false,
+            attributes: vec![("a", "b"), ("c", "d"), ("e", "`")]
+                .into_iter()
+                .collect(),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            MissingWhitespaceBetweenAttributes,
+            UnexpectedCharacterInUnquotedAttributeValue
+        ]
+    );
+}
+
+#[test]
+fn rcdata() {
+    let input = "<title>a&lt;</b></titlex></title>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("title")))
+    );
+    tokenizer.set_state(TokenizerState::RCDATA);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a<</b></titlex>").collect();
+    expected.push(Ok(end_tag("title")));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn rcdata_with_overridden_last_start_tag() {
+    let input = "a</b></textarea>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    tokenizer.set_state(TokenizerState::RCDATA);
+    tokenizer.set_last_start_tag(Some("textarea"));
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a</b>").collect();
+    expected.push(Ok(end_tag("textarea")));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn rawtext() {
+    let input = "<style>a&lt;</STYLE>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("style")))
+    );
+    tokenizer.set_state(TokenizerState::RAWTEXT);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a&lt;").collect();
+    expected.push(Ok(end_tag("style")));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn plaintext() {
+    let input = "<plaintext></plaintext>\0";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("plaintext")))
+    );
+    tokenizer.set_state(TokenizerState::PLAINTEXT);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("</plaintext>").collect();
+    expected.push(Ok(Character('\u{FFFD}')));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert_eq!(errors, [UnexpectedNullCharacter]);
+}
+
+#[test]
+fn script_data() {
+    let input = "<script>if (a<b) {}</script>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("script")))
+    );
+    tokenizer.set_state(TokenizerState::ScriptData);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("if (a<b) {}").collect();
+    expected.push(Ok(end_tag("script")));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn script_data_double_escaped() {
+    let input = "<script><!--<script></script>--></script>";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("script")))
+    );
+    tokenizer.set_state(TokenizerState::ScriptData);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("<!--<script></script>-->").collect();
+    expected.push(Ok(end_tag("script")));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn script_data_escaped_eof() {
+    let input = "<script><!--";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    assert_eq!(
+        tokenizer.next().map(without_span),
+        Some(Ok(start_tag("script")))
+    );
+    tokenizer.set_state(TokenizerState::ScriptData);
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("<!--").collect();
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert_eq!(errors, [EofInScriptHtmlCommentLikeText]);
+}
+
+#[test]
+fn cdata_section_in_foreign_content() {
+    let input = "<![CDATA[a]b]]c]]]>d";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    tokenizer.set_adjusted_current_node_namespace(Some(Namespace::Svg));
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a]b]]c]d").collect();
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn eof_in_cdata_section() {
+    let input = "<![CDATA[a";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    tokenizer.set_adjusted_current_node_namespace(Some(Namespace::MathMl));
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [Ok(Character('a')), Ok(EndOfFile)];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [EofInCdata]);
+}
+
+#[test]
+fn cdata_section_in_html_content() {
+    let input = "<![CDATA[a]]>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Comment {
+            data: String::from("[CDATA[a]]"),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [CdataInHtmlContent]);
+}
+
+#[test]
+fn processing_instruction_is_a_bogus_comment() {
+    let input = "<?xml version=\"1.0\"?>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Comment {
+            data: String::from("?xml version=\"1.0\"?"),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [UnexpectedQuestionMarkInsteadOfTagName]);
+}
+
+#[test]
+fn bogus_comments() {
+    let input = "</ ><!>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Comment {
+            data: String::from(" "),
+        }),
+        Ok(Comment {
+            data: String::new(),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(
+        errors,
+        [InvalidFirstCharacterOfTagName, IncorrectlyOpenedComment]
+    );
+}
+
+#[test]
+fn bogus_doctype() {
+    let input = "<!DOCTYPE html garbage \"x\">";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Doctype {
+            name: Some(String::from("html")),
+            public_identifier: None,
+            system_identifier: None,
+            force_quirks: true,
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [InvalidCharacterSequenceAfterDoctypeName]);
+}
+
+#[test]
+fn eof_in_bogus_doctype() {
+    let input = "<!DOCTYPE html SYSTEM \"a\" b";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Doctype {
+            name: Some(String::from("html")),
+            public_identifier: None,
+            system_identifier: Some(String::from("a")),
+            force_quirks: false,
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [UnexpectedCharacterAfterDoctypeSystemIdentifier]);
+}
+
+#[test]
+fn doctype_decl_with_empty_identifiers() {
+    let input = "<!DOCTYPE html PUBLIC '' ''>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Doctype {
+            name: Some(String::from("html")),
+            public_identifier: Some(String::new()),
+            system_identifier: Some(String::new()),
+            force_quirks: false,
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn self_closing_start_tag() {
+    let input = "<br/><a /b></p/>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Tag {
+            kind: TagKind::Start,
+            tag_name: String::from("br"),
+            self_closing: true,
+            attributes: Attributes::new(),
+        }),
+        Ok(Tag {
+            kind: TagKind::Start,
+            tag_name: String::from("a"),
+            self_closing: false,
+            attributes: vec![("b", "")].into_iter().collect(),
+        }),
+        Ok(Tag {
+            kind: TagKind::End,
+            tag_name: String::from("p"),
+            self_closing: true,
+            attributes: Attributes::new(),
+        }),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(errors, [UnexpectedSolidusInTag, EndTagWithTrailingSolidus]);
+}
+
+#[test]
+fn newlines_are_normalized() {
+    let input = "a\r\nb\rc\n\r\r\n<!--\r\n-->";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a\nb\nc\n\n\n").collect();
+    expected.push(Ok(Comment {
+        data: String::from("\n"),
+    }));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn invalid_codepoints_in_input_stream() {
+    let input = "\u{1}\u{7F}\t\u{FDD0}<a\u{FFFF}>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [
+        Ok(Character('\u{1}')),
+        Ok(Character('\u{7F}')),
+        Ok(Character('\t')),
+        Ok(Character('\u{FDD0}')),
+        Ok(start_tag("a\u{FFFF}")),
+        Ok(EndOfFile),
+    ];
+    assert_eq!(actual, &expected);
+    assert_eq!(
+        errors,
+        [
+            ControlCharacterInInputStream,
+            ControlCharacterInInputStream,
+            NoncharacterInInputStream,
+            NoncharacterInInputStream
+        ]
+    );
+}
+
+#[test]
+fn non_ascii_input() {
+    let input = "<p title=caf\u{E9}>\u{1F600}\r\n\u{E9}";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected = vec![Ok(Tag {
+        kind: TagKind::Start,
+        tag_name: String::from("p"),
+        self_closing: false,
+        attributes: vec![("title", "caf\u{E9}")].into_iter().collect(),
+    })];
+    expected.extend(characters("\u{1F600}\n\u{E9}"));
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn token_spans() {
+    let input = "<p>\r\n&amp;</p>";
+    let mut errors: Vec<Error> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    let actual: Vec<_> = tokenizer.map(|output| output.unwrap().span).collect();
+
+    let expected = [
+        span((1, 1, 0), (1, 4, 3)),
+        span((1, 4, 3), (2, 1, 5)),
+        span((2, 1, 5), (2, 6, 10)),
+        span((2, 6, 10), (2, 10, 14)),
+        span((2, 10, 14), (2, 10, 14)),
+    ];
+    assert_eq!(actual, &expected);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn character_token_spans_in_one_step() {
+    let input = "a&#zb</b>c&amp;";
+    let mut errors: Vec<Error> = Vec::new();
+    let mut tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+    tokenizer.set_state(TokenizerState::RCDATA);
+    tokenizer.set_last_start_tag(Some("title"));
+
+    let actual: Vec<_> = tokenizer
+        .map(|output| {
+            let output = output.unwrap();
+            (output.value, output.span)
+        })
+        .collect();
+
+    let expected = vec![
+        (Character('a'), span((1, 1, 0), (1, 2, 1))),
+        (Character('&'), span((1, 2, 1), (1, 3, 2))),
+        (Character('#'), span((1, 3, 2), (1, 4, 3))),
+        (Character('z'), span((1, 4, 3), (1, 5, 4))),
+        (Character('b'), span((1, 5, 4), (1, 6, 5))),
+        (Character('<'), span((1, 6, 5), (1, 7, 6))),
+        (Character('/'), span((1, 7, 6), (1, 8, 7))),
+        (Character('b'), span((1, 8, 7), (1, 9, 8))),
+        (Character('>'), span((1, 9, 8), (1, 10, 9))),
+        (Character('c'), span((1, 10, 9), (1, 11, 10))),
+        (Character('&'), span((1, 11, 10), (1, 16, 15))),
+        (EndOfFile, span((1, 16, 15), (1, 16, 15))),
+    ];
+    assert_eq!(actual, expected);
+    assert_eq!(errors, [AbsenceOfDigitsInNumericCharacterReference]);
+}
+
+#[test]
+fn characters_of_a_reference_share_its_span() {
+    let input = "&NotEqualTilde;</c>";
+    let mut tokenizer = TokenizerImpl::new(input.into(), Ignore);
+    tokenizer.set_state(TokenizerState::RCDATA);
+    tokenizer.set_last_start_tag(Some("title"));
+
+    let actual: Vec<_> = tokenizer
+        .map(|output| {
+            let output = output.unwrap();
+            (output.value, output.span)
+        })
+        .collect();
+
+    let expected = vec![
+        (Character('\u{2242}'), span((1, 1, 0), (1, 16, 15))),
+        (Character('\u{338}'), span((1, 1, 0), (1, 16, 15))),
+        (Character('<'), span((1, 16, 15), (1, 17, 16))),
+        (Character('/'), span((1, 17, 16), (1, 18, 17))),
+        (Character('c'), span((1, 18, 17), (1, 19, 18))),
+        (Character('>'), span((1, 19, 18), (1, 20, 19))),
+        (EndOfFile, span((1, 20, 19), (1, 20, 19))),
+    ];
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parse_error_span() {
+    let input = "ab\n<=";
+    let mut errors: Vec<Spanned<Error>> = Vec::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut errors);
+
+    tokenizer.for_each(drop);
+
+    let expected = [Spanned::new(
+        Error::from(InvalidFirstCharacterOfTagName),
+        span((2, 2, 4), (2, 3, 5)),
+    )];
+    assert_eq!(errors, &expected);
+    assert_eq!(
+        errors[0].to_string(),
+        "InvalidFirstCharacterOfTagName at 2:2"
+    );
+}
+
+#[test]
+fn fail_fast_error_sink() {
+    let input = "a<=b";
+    let mut error_sink = FailFast::new();
+    let tokenizer = TokenizerImpl::new(input.into(), &mut error_sink);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [Ok(Character('a')), Err(InvalidFirstCharacterOfTagName)];
+    assert_eq!(actual, &expected);
+    let first_error = error_sink.first_error().map(|error| &error.value);
+    assert_eq!(
+        first_error,
+        Some(&Error::from(InvalidFirstCharacterOfTagName))
+    );
+}
+
+#[test]
+fn strict_mode() {
+    let input = "a<=b";
+    let tokenizer = TokenizerImpl::strict(input.into());
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let expected = [Ok(Character('a')), Err(InvalidFirstCharacterOfTagName)];
+    assert_eq!(actual, &expected);
+}
+
+#[test]
+fn ignore_error_sink() {
+    let input = "a<=b";
+    let tokenizer = TokenizerImpl::new(input.into(), Ignore);
+
+    let actual: Vec<_> = tokenizer.map(without_span).collect();
+
+    let mut expected: Vec<_> = characters("a<=b").collect();
+    expected.push(Ok(EndOfFile));
+    assert_eq!(actual, expected);
+}
+
+fn without_span(output: Result<Spanned<Token>>) -> std::result::Result<Token, ParseError> {
+    output.map(|token| token.value).map_err(|error| error.value)
+}
+
+fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+    let position = |(line, column, offset)| SourcePosition {
+        line,
+        column,
+        offset,
+    };
+    Span::new(position(start), position(end))
+}
+
+fn start_tag(tag_name: &str) -> Token {
+    Tag {
+        kind: TagKind::Start,
+        tag_name: String::from(tag_name),
+        self_closing: false,
+        attributes: Attributes::new(),
+    }
+}
+
+fn end_tag(tag_name: &str) -> Token {
+    Tag {
+        kind: TagKind::End,
+        tag_name: String::from(tag_name),
+        self_closing: false,
+        attributes: Attributes::new(),
+    }
+}
+
+fn characters(data: &str) -> impl Iterator<Item = std::result::Result<Token, ParseError>> + '_ {
+    data.chars().map(|ch| Ok(Character(ch)))
 }
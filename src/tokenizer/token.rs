@@ -1,4 +1,6 @@
 use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::slice;
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum Token {
@@ -27,17 +29,19 @@ pub enum TagKind {
     End,
 }
 
+/// The attributes of a tag, in source order. Only the first of several attributes with the same
+/// name is kept.
 #[derive(Clone, Debug)]
 pub struct Attributes {
     attrs: Vec<Attribute>,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Default, PartialEq, Debug)]
 pub struct Attribute(String, String);
 
 pub(in crate::tokenizer) trait IncompleteToken: Debug + Into<Token> {}
 
-#[derive(Debug)]
+#[derive(Default, Debug)]
 pub(in crate::tokenizer) struct IncompleteDoctype {
     pub(in crate::tokenizer) name: Option<String>,
     pub(in crate::tokenizer) public_identifier: Option<String>,
@@ -51,9 +55,11 @@ pub(in crate::tokenizer) struct IncompleteTag {
     pub(in crate::tokenizer) tag_name: String,
     pub(in crate::tokenizer) self_closing: bool,
     pub(in crate::tokenizer) attributes: Attributes,
+    current_attribute: Option<Attribute>,
+    current_attribute_is_duplicate: bool,
 }
 
-#[derive(Debug)]
+#[derive(Default, Debug)]
 pub(in crate::tokenizer) struct IncompleteComment {
     pub(in crate::tokenizer) data: String,
 }
@@ -65,9 +71,69 @@ impl From<char> for Token {
 }
 
 impl Attributes {
-    pub(in crate::tokenizer) fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self { attrs: Vec::new() }
     }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    /// Returns the value of the attribute with the given name, if there is one
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.name() == name)
+            .map(Attribute::value)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Attribute> {
+        self.attrs.iter()
+    }
+
+    /// Returns `false` (and drops the attribute) if there is already an attribute with the same
+    /// name
+    pub(crate) fn push(&mut self, attr: Attribute) -> bool {
+        if self.contains(attr.name()) {
+            false
+        } else {
+            self.attrs.push(attr);
+            true
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Attributes {
+    type Item = &'a Attribute;
+    type IntoIter = slice::Iter<'a, Attribute>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for Attributes {
+    //! ```
+    //! use dominoes::tokenizer::Attributes;
+    //! let attrs: Attributes = vec![("id", "a"), ("class", "b"), ("id", "c")].into_iter().collect();
+    //! assert_eq!(attrs.len(), 2);
+    //! assert_eq!(attrs.get("id"), Some("a"));
+    //! ```
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> Self {
+        let mut attrs = Self::new();
+        for (name, value) in iter {
+            attrs.push(Attribute(name.into(), value.into()));
+        }
+        attrs
+    }
 }
 
 impl PartialEq<Self> for Attributes {
@@ -95,29 +161,24 @@ impl Attribute {
     pub fn value(&self) -> &str {
         &self.1
     }
-}
 
-impl Default for IncompleteDoctype {
-    fn default() -> Self {
-        Self {
-            name: None,
-            public_identifier: None,
-            system_identifier: None,
-            force_quirks: false,
-        }
+    pub(in crate::tokenizer) fn name_mut(&mut self) -> &mut String {
+        &mut self.0
+    }
+
+    pub(in crate::tokenizer) fn value_mut(&mut self) -> &mut String {
+        &mut self.1
     }
 }
 
-impl Into<Token> for IncompleteDoctype {
-    fn into(self) -> Token {
-        debug_assert!(!is_empty_some(&self.name));
-        debug_assert!(!is_empty_some(&self.public_identifier));
-        debug_assert!(!is_empty_some(&self.system_identifier));
+impl From<IncompleteDoctype> for Token {
+    fn from(doctype: IncompleteDoctype) -> Self {
+        debug_assert!(!is_empty_some(&doctype.name));
         Token::Doctype {
-            name: self.name,
-            public_identifier: self.public_identifier,
-            system_identifier: self.system_identifier,
-            force_quirks: self.force_quirks,
+            name: doctype.name,
+            public_identifier: doctype.public_identifier,
+            system_identifier: doctype.system_identifier,
+            force_quirks: doctype.force_quirks,
         }
     }
 }
@@ -129,34 +190,55 @@ impl IncompleteTag {
             tag_name: String::new(),
             self_closing: false,
             attributes: Attributes::new(),
+            current_attribute: None,
+            current_attribute_is_duplicate: false,
         }
     }
-}
 
-impl Into<Token> for IncompleteTag {
-    fn into(self) -> Token {
-        debug_assert!(!self.tag_name.is_empty());
-        Token::Tag {
-            kind: self.kind,
-            tag_name: self.tag_name,
-            self_closing: self.self_closing,
-            attributes: self.attributes,
+    pub(in crate::tokenizer) fn start_new_attribute(&mut self) {
+        self.finish_current_attribute();
+        self.current_attribute = Some(Attribute::default());
+    }
+
+    pub(in crate::tokenizer) fn current_attribute(&mut self) -> &mut Attribute {
+        self.current_attribute.as_mut().unwrap()
+    }
+
+    /// Must be called when leaving the attribute name state. Returns `false` if the current
+    /// attribute has the same name as a previous one, in which case it will be dropped.
+    pub(in crate::tokenizer) fn check_current_attribute_name(&mut self) -> bool {
+        let name = self.current_attribute.as_ref().unwrap().name();
+        self.current_attribute_is_duplicate = self.attributes.contains(name);
+        !self.current_attribute_is_duplicate
+    }
+
+    pub(in crate::tokenizer) fn finish_current_attribute(&mut self) {
+        if let Some(attr) = self.current_attribute.take() {
+            if !self.current_attribute_is_duplicate {
+                let pushed = self.attributes.push(attr);
+                debug_assert!(pushed);
+            }
         }
+        self.current_attribute_is_duplicate = false;
     }
 }
 
-impl Default for IncompleteComment {
-    fn default() -> Self {
-        Self {
-            data: String::new(),
+impl From<IncompleteTag> for Token {
+    fn from(mut tag: IncompleteTag) -> Self {
+        debug_assert!(!tag.tag_name.is_empty());
+        tag.finish_current_attribute();
+        Token::Tag {
+            kind: tag.kind,
+            tag_name: tag.tag_name,
+            self_closing: tag.self_closing,
+            attributes: tag.attributes,
         }
     }
 }
 
-impl Into<Token> for IncompleteComment {
-    fn into(self) -> Token {
-        debug_assert!(!self.data.is_empty());
-        Token::Comment { data: self.data }
+impl From<IncompleteComment> for Token {
+    fn from(comment: IncompleteComment) -> Self {
+        Token::Comment { data: comment.data }
     }
 }
 
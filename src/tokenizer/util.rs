@@ -1,8 +1,14 @@
 use std::convert::TryInto;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::error::ParseError;
+use super::span::SourcePosition;
 
 pub(in crate::tokenizer) struct CodepointStream {
     source: String,
-    cursor: usize,
+    /// Position of the next character to be consumed
+    cursor: SourcePosition,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -12,34 +18,82 @@ pub(in crate::tokenizer) enum Codepoint {
 }
 
 impl CodepointStream {
+    /// Takes time proportional to the offset, which is fine because the tokenizer never needs to
+    /// look very far ahead
     pub(in crate::tokenizer) fn peek(&self, offset: usize) -> Codepoint {
-        self.source
-            .chars()
-            .nth(self.cursor + offset)
-            .map(Codepoint::from)
+        self.remaining()
+            .nth(offset)
+            .map(|(ch, _)| Codepoint::from(ch))
             .unwrap_or(Codepoint::EndOfFile)
     }
 
-    pub(in crate::tokenizer) fn advance(&mut self, count: usize) -> bool {
-        if self.cursor + count < self.source.len() {
-            self.cursor += count;
-            true
-        } else {
-            self.cursor = self.source.len();
-            false
+    pub(in crate::tokenizer) fn consume_next(&mut self) -> Codepoint {
+        match self.remaining().next() {
+            Some((ch, len)) => {
+                self.cursor = self.cursor.after(ch, len);
+                Codepoint::from(ch)
+            }
+            None => Codepoint::EndOfFile,
         }
     }
 
-    pub(in crate::tokenizer) fn consume_next(&mut self) -> Codepoint {
-        let codepoint = self.peek(0);
-        self.advance(1);
-        codepoint
+    /// Returns the position of the next character to be consumed
+    pub(in crate::tokenizer) fn position(&self) -> SourcePosition {
+        self.cursor
+    }
+
+    /// Returns the character of the source text at the position, along with the length in bytes of
+    /// the source text it stands for
+    pub(in crate::tokenizer) fn character_at(
+        &self,
+        position: SourcePosition,
+    ) -> Option<(char, usize)> {
+        NormalizeNewlines::new(self.source[position.offset..].chars()).next()
+    }
+
+    fn remaining(&self) -> NormalizeNewlines<Chars<'_>> {
+        NormalizeNewlines::new(self.source[self.cursor.offset..].chars())
+    }
+}
+
+/// Normalizes newlines in the input stream, as the spec requires before tokenization: each CR LF
+/// pair and each lone CR is replaced by a single LF. Yields each character along with the length
+/// in bytes of the source text it stands for.
+struct NormalizeNewlines<I: Iterator<Item = char>> {
+    source: Peekable<I>,
+}
+
+impl<I: Iterator<Item = char>> NormalizeNewlines<I> {
+    fn new(source: I) -> Self {
+        Self {
+            source: source.peekable(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for NormalizeNewlines<I> {
+    type Item = (char, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.source.next()? {
+            '\r' => {
+                if self.source.next_if_eq(&'\n').is_some() {
+                    Some(('\n', 2))
+                } else {
+                    Some(('\n', 1))
+                }
+            }
+            ch => Some((ch, ch.len_utf8())),
+        }
     }
 }
 
 impl From<String> for CodepointStream {
     fn from(source: String) -> Self {
-        Self { source, cursor: 0 }
+        Self {
+            source,
+            cursor: SourcePosition::START,
+        }
     }
 }
 
@@ -82,9 +136,9 @@ impl From<char> for Codepoint {
     }
 }
 
-impl Into<u32> for Codepoint {
-    fn into(self) -> u32 {
-        match self {
+impl From<Codepoint> for u32 {
+    fn from(codepoint: Codepoint) -> Self {
+        match codepoint {
             Codepoint::Scalar(ch) => ch.into(),
             Codepoint::EndOfFile => 0xffffffff,
         }
@@ -101,3 +155,69 @@ impl TryInto<char> for Codepoint {
         }
     }
 }
+
+/// Returns the parse error, if any, for a character appearing in the input stream. Surrogates are
+/// never reported because they can't occur in a `String`: they will already have been replaced when
+/// the input was decoded.
+pub(in crate::tokenizer) fn input_stream_error(ch: char) -> Option<ParseError> {
+    let code = u32::from(ch);
+    if is_noncharacter(code) {
+        Some(ParseError::NoncharacterInInputStream)
+    } else if is_control(code) && !is_ascii_whitespace(code) && code != 0x00 {
+        Some(ParseError::ControlCharacterInInputStream)
+    } else {
+        None
+    }
+}
+
+pub(in crate::tokenizer) fn is_surrogate(code: u32) -> bool {
+    (0xD800..=0xDFFF).contains(&code)
+}
+
+pub(in crate::tokenizer) fn is_noncharacter(code: u32) -> bool {
+    (0xFDD0..=0xFDEF).contains(&code) || (code <= 0x10FFFF && (code & 0xFFFE) == 0xFFFE)
+}
+
+pub(in crate::tokenizer) fn is_control(code: u32) -> bool {
+    code <= 0x1F || (0x7F..=0x9F).contains(&code)
+}
+
+pub(in crate::tokenizer) fn is_ascii_whitespace(code: u32) -> bool {
+    matches!(code, 0x09 | 0x0A | 0x0C | 0x0D | 0x20)
+}
+
+/// Some numeric character references in the C1 control range are treated as if they referred to
+/// the corresponding Windows-1252 character instead
+pub(in crate::tokenizer) fn numeric_character_reference_replacement(code: u32) -> Option<u32> {
+    let replacement = match code {
+        0x80 => 0x20AC,
+        0x82 => 0x201A,
+        0x83 => 0x0192,
+        0x84 => 0x201E,
+        0x85 => 0x2026,
+        0x86 => 0x2020,
+        0x87 => 0x2021,
+        0x88 => 0x02C6,
+        0x89 => 0x2030,
+        0x8A => 0x0160,
+        0x8B => 0x2039,
+        0x8C => 0x0152,
+        0x8E => 0x017D,
+        0x91 => 0x2018,
+        0x92 => 0x2019,
+        0x93 => 0x201C,
+        0x94 => 0x201D,
+        0x95 => 0x2022,
+        0x96 => 0x2013,
+        0x97 => 0x2014,
+        0x98 => 0x02DC,
+        0x99 => 0x2122,
+        0x9A => 0x0161,
+        0x9B => 0x203A,
+        0x9C => 0x0153,
+        0x9E => 0x017E,
+        0x9F => 0x0178,
+        _ => return None,
+    };
+    Some(replacement)
+}